@@ -66,7 +66,7 @@ fn main() -> std::io::Result<()> {
     Ok(())
 }
 
-fn concat_digits(first: &String, last: &String) -> Option<u32> {
+fn concat_digits(first: &str, last: &str) -> Option<u32> {
     let mut str = String::new();
     str.push_str(first);
     str.push_str(last);
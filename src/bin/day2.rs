@@ -10,6 +10,7 @@ struct Game {
     draws: Vec<CubeSet>,
 }
 
+#[derive(Clone, Copy)]
 struct CubeSet {
     red: u8,
     green: u8,
@@ -31,18 +32,18 @@ fn main() -> std::io::Result<()> {
     Ok(())
 }
 
-fn part1(input: &String) {
+fn part1(input: &str) {
     let id_sum: i32 = input
         .lines()
         .flat_map(parse_line)
         .filter(|game| game.draws.iter().all(has_valid_cubes))
         .map(|game| game.id)
-        .fold(0, |x, y| x as i32 + y as i32);
+        .fold(0, |x, y| x + y as i32);
 
     println!("Part 1: {}", id_sum);
 }
 
-fn part2(input: &String) {
+fn part2(input: &str) {
     let power_sum: i32 = input
         .lines()
         .flat_map(parse_line)
@@ -73,7 +74,7 @@ fn has_valid_cubes(draw: &CubeSet) -> bool {
 
 fn parse_line(line: &str) -> Option<Game> {
     let parts: Vec<&str> = line.split(":").collect();
-    let id = parts.get(0).and_then(|s| {
+    let id = parts.first().and_then(|s| {
         let sp = s.split(" ").collect::<Vec<_>>();
         sp.into_iter().last()
     });
@@ -103,17 +104,17 @@ fn parse_draw(raw: &str) -> CubeSet {
 
     let red = pairs
         .iter()
-        .find(|(_, color)| color.to_owned() == "red")
+        .find(|(_, color)| *color == "red")
         .map(|(n, _)| n)
         .unwrap_or(&0);
     let green = pairs
         .iter()
-        .find(|(_, color)| color.to_owned() == "green")
+        .find(|(_, color)| *color == "green")
         .map(|(n, _)| n)
         .unwrap_or(&0);
     let blue = pairs
         .iter()
-        .find(|(_, color)| color.to_owned() == "blue")
+        .find(|(_, color)| *color == "blue")
         .map(|(n, _)| n)
         .unwrap_or(&0);
 
@@ -123,3 +124,30 @@ fn parse_draw(raw: &str) -> CubeSet {
         blue: *blue,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use advent_of_code_2023::generate::{day2, Rng};
+
+    use crate::{minimum_set, parse_line, CubeSet};
+
+    #[test]
+    fn test_minimum_set_is_monotone() {
+        let mut rng = Rng::new(2);
+        let at_least =
+            |a: &CubeSet, b: &CubeSet| a.red >= b.red && a.green >= b.green && a.blue >= b.blue;
+        for line in day2(&mut rng, 40).lines() {
+            let red = rng.below(20);
+            let extended = format!("{}; {} red, {} blue", line, red, rng.below(20));
+            let game = parse_line(line).unwrap();
+            let draws = game.draws.clone();
+            let minimum = minimum_set(game);
+            let extended = minimum_set(parse_line(&extended).unwrap());
+
+            assert!(draws.iter().all(|draw| at_least(&minimum, draw)));
+            assert!(at_least(&extended, &minimum));
+            assert!(extended.red as u64 >= red);
+            assert!(extended.power() >= minimum.power());
+        }
+    }
+}
@@ -18,16 +18,16 @@ fn main() -> std::io::Result<()> {
     Ok(())
 }
 
-fn part1(input: &String) -> i32 {
-    let symbol_locations: HashSet<(usize, usize)> = get_symbol_locations(&input, is_symbol);
-    let nums = get_nums(&input);
+fn part1(input: &str) -> i32 {
+    let symbol_locations: HashSet<(usize, usize)> = get_symbol_locations(input, is_symbol);
+    let nums = get_nums(input);
     sum_part_ids(nums, &symbol_locations)
 }
 
-fn part2(input: &String) -> i32 {
+fn part2(input: &str) -> i32 {
     let symbol_locations: HashSet<(usize, usize)> =
-        get_symbol_locations(&input, |char| char.to_owned() == '*');
-    let nums = get_nums(&input);
+        get_symbol_locations(input, |char| *char == '*');
+    let nums = get_nums(input);
     symbol_locations
         .iter()
         .filter_map(|(x, y)| {
@@ -49,7 +49,7 @@ fn sum_part_ids(nums: Vec<PointValue>, symbol_locations: &HashSet<(usize, usize)
         .sum()
 }
 
-fn get_nums(input: &String) -> Vec<PointValue> {
+fn get_nums(input: &str) -> Vec<PointValue> {
     let mut nums: Vec<PointValue> = Vec::new();
     let mut num_builder: String = String::new();
 
@@ -78,10 +78,7 @@ fn get_nums(input: &String) -> Vec<PointValue> {
     nums
 }
 
-fn get_symbol_locations(
-    input: &String,
-    is_symbol: impl Fn(&char) -> bool,
-) -> HashSet<(usize, usize)> {
+fn get_symbol_locations(input: &str, is_symbol: impl Fn(&char) -> bool) -> HashSet<(usize, usize)> {
     input
         .lines()
         .enumerate()
@@ -92,7 +89,7 @@ fn get_symbol_locations(
 }
 
 fn is_symbol(char: &char) -> bool {
-    !char.is_numeric() && char.to_owned() != '.'
+    !char.is_numeric() && *char != '.'
 }
 
 fn get_neighbors(point_value: &PointValue) -> HashSet<(isize, isize)> {
@@ -105,10 +102,7 @@ fn get_neighbors(point_value: &PointValue) -> HashSet<(isize, isize)> {
     let middle: Vec<(isize, isize)> = vec![(x, y - 1), (x, y + len)];
     let bottom: Vec<(isize, isize)> = (y - 1..y + len + 1).map(|y| (x + 1, y)).collect();
 
-    top.into_iter()
-        .chain(middle.into_iter())
-        .chain(bottom.into_iter())
-        .collect()
+    top.into_iter().chain(middle).chain(bottom).collect()
 }
 
 fn is_part(n: &PointValue, symbol_locations: &HashSet<(usize, usize)>) -> bool {
@@ -120,7 +114,9 @@ fn is_part(n: &PointValue, symbol_locations: &HashSet<(usize, usize)>) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use crate::{get_nums, get_symbol_locations, part1, PointValue, is_symbol};
+    use advent_of_code_2023::generate::{day3, Rng};
+
+    use crate::{get_nums, get_symbol_locations, is_symbol, part1, PointValue};
 
     #[test]
     fn test_parsing() {
@@ -245,4 +241,24 @@ mod tests {
         let actual = part1(&input);
         assert_eq!(sum, actual, "expected: {} != actual: {}", sum, actual);
     }
+
+    #[test]
+    fn test_nums_match_grid() {
+        let mut rng = Rng::new(3);
+        for side in 1..40 {
+            let input = day3(&mut rng, side);
+            let grid: Vec<&str> = input.lines().collect();
+            let nums = get_nums(&input);
+
+            for num in &nums {
+                let (row, col) = num.start;
+                let line = grid[row];
+                assert_eq!(num.value.to_string(), line[col..col + num.len]);
+                assert!(!line[..col].ends_with(|char: char| char.is_ascii_digit()));
+                assert!(!line[col + num.len..].starts_with(|char: char| char.is_ascii_digit()));
+            }
+            let digits = input.chars().filter(char::is_ascii_digit).count();
+            assert_eq!(digits, nums.iter().map(|num| num.len).sum::<usize>());
+        }
+    }
 }
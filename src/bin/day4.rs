@@ -1,81 +1,320 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt, fs,
+    hint::black_box,
+    path::Path,
+    time::Instant,
+};
 
-use advent_of_code_2023::{read_input, Day};
+use advent_of_code_2023::{generate::Rng, read_input, Day};
+
+/// A multiset of card numbers, stored as a bitset for numbers below 128 and a
+/// `HashSet` for anything larger, with any further copies of a number kept
+/// aside in `repeats`.
+#[derive(Default)]
+struct NumSet {
+    bits: u128,
+    overflow: HashSet<usize>,
+    repeats: Vec<usize>,
+}
+
+impl NumSet {
+    fn insert(&mut self, n: usize) {
+        if self.contains(n) {
+            self.repeats.push(n);
+        } else if n < u128::BITS as usize {
+            self.bits |= 1 << n;
+        } else {
+            self.overflow.insert(n);
+        }
+    }
+
+    /// How many of `self`'s numbers, counting every copy, are in `other`.
+    /// Copies in `other` don't count again.
+    fn count_common(&self, other: &NumSet) -> usize {
+        (self.bits & other.bits).count_ones() as usize
+            + self.overflow.intersection(&other.overflow).count()
+            + self.repeats.iter().filter(|&&n| other.contains(n)).count()
+    }
+
+    fn contains(&self, n: usize) -> bool {
+        if n < u128::BITS as usize {
+            self.bits & (1 << n) != 0
+        } else {
+            self.overflow.contains(&n)
+        }
+    }
+}
+
+impl FromIterator<usize> for NumSet {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        let mut set = NumSet::default();
+        for n in iter {
+            set.insert(n);
+        }
+        set
+    }
+}
 
 struct Card {
-    nums: Vec<usize>,
-    winning_nums: HashSet<usize>,
+    id: usize,
+    nums: NumSet,
+    winning_nums: NumSet,
+}
+
+/// How many points a card is worth for a given number of matches.
+#[derive(Debug, PartialEq, Eq)]
+enum Scoring {
+    /// 1 point for the first match, doubled for every match after it.
+    Doubling,
+    /// 1 point per match.
+    Linear,
+    /// 1, 2, 3, 5, 8, ... points for 1, 2, 3, 4, 5, ... matches.
+    Fibonacci,
+    /// Points indexed by match count; counts past the end of the table score 0.
+    Table(Vec<u64>),
+}
+
+impl Scoring {
+    /// Scores too large for a `u64` saturate at `u64::MAX`.
+    fn score(&self, matches: usize) -> u64 {
+        match self {
+            Scoring::Doubling if matches == 0 => 0,
+            Scoring::Doubling => u64::saturating_pow(2, (matches - 1).min(64) as u32),
+            Scoring::Linear => matches as u64,
+            Scoring::Fibonacci if matches == 0 => 0,
+            Scoring::Fibonacci => {
+                (1..matches)
+                    .fold((1u64, 1u64), |(a, b), _| (b, a.saturating_add(b)))
+                    .1
+            }
+            Scoring::Table(table) => table.get(matches).copied().unwrap_or(0),
+        }
+    }
+
+    /// Reads a table of whitespace separated scores, the first being for 0 matches.
+    fn from_table_file(path: impl AsRef<Path>) -> std::io::Result<Scoring> {
+        fs::read_to_string(path)?
+            .split_whitespace()
+            .map(|n| n.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map(Scoring::Table)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// Reads `doubling`, `linear`, `fibonacci` or `table:PATH`.
+    fn from_arg(arg: &str) -> std::io::Result<Scoring> {
+        match arg {
+            "doubling" => Ok(Scoring::Doubling),
+            "linear" => Ok(Scoring::Linear),
+            "fibonacci" => Ok(Scoring::Fibonacci),
+            _ => match arg.strip_prefix("table:") {
+                Some(path) => Scoring::from_table_file(path),
+                None => Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "unknown scoring '{}', expected doubling, linear, fibonacci or table:PATH",
+                        arg
+                    ),
+                )),
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct CardScore {
+    id: usize,
+    matches: usize,
+    score: u64,
 }
 
 fn main() -> std::io::Result<()> {
     let input = read_input(Day::DAY4)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let overflow = if args.iter().any(|arg| arg == "--clamp") {
+        Overflow::Clamp
+    } else {
+        Overflow::Error
+    };
+    let scoring = match args.iter().find_map(|arg| arg.strip_prefix("--scoring=")) {
+        Some(arg) => Scoring::from_arg(arg)?,
+        None => Scoring::Doubling,
+    };
 
-    println!("Part 1: {}", part1(&input));
-    println!("Part 2: {}", part2(&input));
+    if let Some(size) = args.iter().find_map(|arg| arg.strip_prefix("--bench=")) {
+        let size = size
+            .parse()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        bench(size);
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--breakdown") {
+        for card in breakdown(&input, &scoring) {
+            println!(
+                "Card {}: {} matches, {} points",
+                card.id, card.matches, card.score
+            );
+        }
+    }
+
+    println!("Part 1: {}", part1(&input, &scoring));
+    println!("Part 2: {}", part2(&input, overflow).map_err(invalid_data)?);
 
     Ok(())
 }
 
-fn part1(input: &String) -> i32 {
-    let cards: Vec<Card> = parse_cards(&input);
+fn invalid_data(error: OverflowError) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+}
+
+fn part1(input: &str, scoring: &Scoring) -> u64 {
+    breakdown(input, scoring)
+        .iter()
+        .map(|card| card.score)
+        .fold(0, u64::saturating_add)
+}
 
-    cards
+fn breakdown(input: &str, scoring: &Scoring) -> Vec<CardScore> {
+    parse_cards(input)
         .iter()
-        .map(num_matches)
-        .filter(|matches| matches > &0)
-        .map(|matches| i32::pow(2, (matches - 1) as u32))
-        .sum()
+        .map(|card| {
+            let matches = num_matches(card);
+            CardScore {
+                id: card.id,
+                matches,
+                score: scoring.score(matches),
+            }
+        })
+        .collect()
+}
+
+/// What to do when a card wins copies of cards past the end of the table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Overflow {
+    /// Only copy the cards that exist, ignoring the rest of the win.
+    Clamp,
+    /// Reject the table with an [`OverflowError`].
+    Error,
 }
 
-fn part2(input: &String) -> usize {
-    let cards: Vec<Card> = parse_cards(&input);
+#[derive(Debug, PartialEq, Eq)]
+struct OverflowError {
+    card_id: usize,
+    matches: usize,
+    remaining: usize,
+}
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "card {} wins copies of the next {} cards, but only {} follow it",
+            self.card_id, self.matches, self.remaining
+        )
+    }
+}
+impl Error for OverflowError {}
 
-    let cards: Vec<usize> = cards.iter().map(num_matches).collect();
+fn part2(input: &str, overflow: Overflow) -> Result<usize, OverflowError> {
+    let cards: Vec<Card> = parse_cards(input);
 
     let len = cards.len();
     let mut map: HashMap<usize, usize> = HashMap::new();
     let mut count = 0;
 
-    for (ri, card) in cards.into_iter().rev().enumerate() {
+    for (ri, card) in cards.iter().rev().enumerate() {
         let i = len - ri - 1;
-        if card == 0 {
-            map.insert(i, 1);
-            count += 1;
-        } else {
-            let sum: usize = (i + 1..i + 1 + card).map(|j| map.get(&j).unwrap()).sum();
-            let sum = sum + 1;
-            map.insert(i, sum);
-            count += sum;
+        let matches = num_matches(card);
+        let remaining = len - i - 1;
+        if matches > remaining && overflow == Overflow::Error {
+            return Err(OverflowError {
+                card_id: card.id,
+                matches,
+                remaining,
+            });
         }
+        let end = i + 1 + matches.min(remaining);
+        let sum: usize = (i + 1..end).map(|j| map.get(&j).unwrap()).sum();
+        let sum = sum + 1;
+        map.insert(i, sum);
+        count += sum;
     }
-    count
+    Ok(count)
 }
 
 fn num_matches(c: &Card) -> usize {
-    c.nums.iter().filter(|n| c.winning_nums.contains(n)).count()
+    c.nums.count_common(&c.winning_nums)
 }
 
-fn parse_cards(input: &String) -> Vec<Card> {
+/// Times `num_matches` against the `HashSet` probing it replaced on a
+/// generated deck of `size` cards.
+fn bench(size: usize) {
+    let rng = &mut Rng::new(0);
+    let mut draw = |count: usize| -> Vec<usize> {
+        rng.distinct(count, 1, 99)
+            .into_iter()
+            .map(|n| n as usize)
+            .collect()
+    };
+    let deck: Vec<(Vec<usize>, Vec<usize>)> = (0..size).map(|_| (draw(10), draw(25))).collect();
+
+    let hashed: Vec<(&Vec<usize>, HashSet<usize>)> = deck
+        .iter()
+        .map(|(winning_nums, nums)| (nums, winning_nums.iter().copied().collect()))
+        .collect();
+    let start = Instant::now();
+    let expected: usize = hashed
+        .iter()
+        .map(|(nums, winning_nums)| nums.iter().filter(|n| winning_nums.contains(n)).count())
+        .map(black_box)
+        .sum();
+    println!("HashSet: {:?}", start.elapsed());
+
+    let cards: Vec<Card> = deck
+        .iter()
+        .zip(1..)
+        .map(|((winning_nums, nums), id)| Card {
+            id,
+            nums: nums.iter().copied().collect(),
+            winning_nums: winning_nums.iter().copied().collect(),
+        })
+        .collect();
+    let start = Instant::now();
+    let actual: usize = cards.iter().map(num_matches).map(black_box).sum();
+    println!("Bitset: {:?}", start.elapsed());
+
+    assert_eq!(expected, actual);
+}
+
+fn parse_cards(input: &str) -> Vec<Card> {
     input
         .lines()
-        .flat_map(|line| line.split(":").nth(1))
-        .flat_map(|rest| parse_card(rest))
+        .flat_map(|line| line.split_once(':'))
+        .flat_map(|(head, rest)| parse_card(head, rest))
         .collect()
 }
 
-fn parse_card(rest: &str) -> Option<Card> {
+fn parse_card(head: &str, rest: &str) -> Option<Card> {
+    let id = head.split_whitespace().last()?.parse().ok()?;
+
     if let [winning_nums, nums] = rest.split("|").collect::<Vec<_>>().as_slice() {
-        let nums: Vec<usize> = nums
+        let nums: NumSet = nums
             .trim()
             .split(" ")
             .flat_map(|n| n.trim().parse::<usize>())
             .collect();
-        let winning_nums: HashSet<usize> = winning_nums
+        let winning_nums: NumSet = winning_nums
             .trim()
             .split(" ")
             .flat_map(|n| n.trim().parse::<usize>())
             .collect();
-        Some(Card { nums, winning_nums })
+        Some(Card {
+            id,
+            nums,
+            winning_nums,
+        })
     } else {
         None
     }
@@ -83,7 +322,12 @@ fn parse_card(rest: &str) -> Option<Card> {
 
 #[cfg(test)]
 mod tests {
-    use crate::part2;
+    use advent_of_code_2023::{differential::compare, generate::generate, Day};
+
+    use crate::{
+        breakdown, num_matches, parse_cards, part1, part2, CardScore, NumSet, Overflow,
+        OverflowError, Scoring,
+    };
 
     #[test]
     fn test() {
@@ -98,6 +342,154 @@ mod tests {
         .trim()
         .to_string();
 
-        assert_eq!(30, part2(&input));
+        assert_eq!(13, part1(&input, &Scoring::Doubling));
+        assert_eq!(Ok(30), part2(&input, Overflow::Error));
+        assert_eq!(Ok(30), part2(&input, Overflow::Clamp));
+    }
+
+    #[test]
+    fn test_repeated_numbers() {
+        let input = r#"
+            Card 1: 5 | 5 5
+            Card 2: 1 | 2
+            Card 3: 1 | 2
+        "#
+        .trim();
+
+        assert_eq!(2, num_matches(&parse_cards(input)[0]));
+        assert_eq!(Ok(5), part2(input, Overflow::Error));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let input = r#"
+            Card 1: 1 2 3 | 1 2 9
+            Card 2: 4 5 6 | 4 5 6
+            Card 3: 7 8 9 | 1 2 3
+        "#
+        .trim()
+        .to_string();
+
+        assert_eq!(
+            Err(OverflowError {
+                card_id: 2,
+                matches: 3,
+                remaining: 1
+            }),
+            part2(&input, Overflow::Error)
+        );
+        assert_eq!(Ok(7), part2(&input, Overflow::Clamp));
+        assert_eq!(
+            "card 2 wins copies of the next 3 cards, but only 1 follow it",
+            part2(&input, Overflow::Error).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_scoring() {
+        let scores = |scoring: Scoring| (0..6).map(|m| scoring.score(m)).collect::<Vec<_>>();
+
+        assert_eq!(vec![0, 1, 2, 4, 8, 16], scores(Scoring::Doubling));
+        assert_eq!(vec![0, 1, 2, 3, 4, 5], scores(Scoring::Linear));
+        assert_eq!(vec![0, 1, 2, 3, 5, 8], scores(Scoring::Fibonacci));
+        assert_eq!(
+            vec![0, 10, 20, 0, 0, 0],
+            scores(Scoring::Table(vec![0, 10, 20]))
+        );
+        assert_eq!(1 << 63, Scoring::Doubling.score(64));
+        assert_eq!(u64::MAX, Scoring::Doubling.score(65));
+        assert_eq!(7540113804746346429, Scoring::Fibonacci.score(91));
+        assert_eq!(u64::MAX, Scoring::Fibonacci.score(200));
+    }
+
+    #[test]
+    fn test_scoring_from_arg() {
+        assert_eq!(Scoring::Fibonacci, Scoring::from_arg("fibonacci").unwrap());
+        let error = Scoring::from_arg("fibonaci").unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, error.kind());
+        assert_eq!(
+            "unknown scoring 'fibonaci', expected doubling, linear, fibonacci or table:PATH",
+            error.to_string()
+        );
+        assert_eq!(
+            std::io::ErrorKind::NotFound,
+            Scoring::from_arg("table:no/such/file").unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let input = r#"
+            Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+            Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+        "#
+        .trim();
+
+        assert_eq!(
+            vec![
+                CardScore {
+                    id: 1,
+                    matches: 4,
+                    score: 4
+                },
+                CardScore {
+                    id: 2,
+                    matches: 2,
+                    score: 2
+                },
+            ],
+            breakdown(input, &Scoring::Linear)
+        );
+    }
+
+    #[test]
+    fn test_num_set() {
+        let set1: NumSet = [1, 5, 5, 127, 128, 1000, 1000].into_iter().collect();
+        let set2: NumSet = [5, 127, 128, 999, 1000, 1000].into_iter().collect();
+
+        assert_eq!(
+            vec![1, 5, 127, 128, 1000],
+            (0..2000).filter(|&n| set1.contains(n)).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![5, 1000], set1.repeats);
+        assert_eq!(6, set1.count_common(&set2));
+        assert_eq!(5, set2.count_common(&set1));
+        assert_eq!(0, set1.count_common(&NumSet::default()));
+    }
+
+    #[test]
+    fn test_generated() {
+        for seed in 0..20 {
+            let input = generate(Day::DAY4, seed, 50);
+
+            assert!(part2(&input, Overflow::Error).is_ok());
+            assert_eq!(
+                part2(&input, Overflow::Clamp),
+                part2(&input, Overflow::Error)
+            );
+        }
+    }
+
+    /// Reference for [`part2`] that deals out every copy one at a time.
+    fn part2_literal(input: &str) -> usize {
+        let matches: Vec<usize> = parse_cards(input).iter().map(num_matches).collect();
+        let mut pending: Vec<usize> = (0..matches.len()).collect();
+        let mut count = 0;
+        while let Some(i) = pending.pop() {
+            count += 1;
+            pending.extend(i + 1..(i + 1 + matches[i]).min(matches.len()));
+        }
+        count
+    }
+
+    #[test]
+    fn test_differential() {
+        compare(
+            0..20,
+            |seed| generate(Day::DAY4, seed, 30),
+            part2_literal,
+            |input| part2(input, Overflow::Clamp).unwrap(),
+        )
+        .unwrap_or_else(|disagreement| panic!("{}", disagreement));
     }
 }
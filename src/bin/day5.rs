@@ -1,3 +1,9 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    fmt,
+};
+
 use advent_of_code_2023::{read_input, Day};
 use itertools::Itertools;
 use regex::Regex;
@@ -6,6 +12,7 @@ struct Range {
     input: u64,
     output: u64,
     len: u64,
+    line: usize,
 }
 impl Range {
     fn get(&self, num: u64) -> Option<u64> {
@@ -16,8 +23,18 @@ impl Range {
         }
     }
 
+    /// The range mapping outputs back to the inputs they came from.
+    fn inverse(&self) -> Range {
+        Range {
+            input: self.output,
+            output: self.input,
+            len: self.len,
+            line: self.line,
+        }
+    }
+
     fn input_end(&self) -> u64 {
-        return self.input + self.len - 1;
+        self.input + self.len - 1
     }
 
     fn get_overlap(&self, range: (u64, u64)) -> Overlap {
@@ -66,6 +83,8 @@ struct Overlap {
 }
 
 struct Map {
+    source: String,
+    destination: String,
     ranges: Vec<Range>,
 }
 impl Map {
@@ -76,6 +95,13 @@ impl Map {
             .unwrap_or(seed)
     }
 
+    /// The range that maps `seed`, if any.
+    fn find_range(&self, seed: u64) -> Option<&Range> {
+        self.ranges.iter().find(|range| range.get(seed).is_some())
+    }
+
+    /// Stage-by-stage reference for mapping a range through [`Piecewise::get_range`].
+    #[cfg(test)]
     fn map_range(&self, range: (u64, u64)) -> Vec<(u64, u64)> {
         let (matching, out) =
             self.ranges
@@ -101,112 +127,741 @@ impl Map {
                 });
         [matching, out].concat()
     }
+
+    fn lint(&self) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        for range in &self.ranges {
+            if range.len == 0 {
+                lints.push(Lint::ZeroLength { line: range.line });
+            }
+            if range.input.checked_add(range.len).is_none()
+                || range.output.checked_add(range.len).is_none()
+            {
+                lints.push(Lint::Overflow { line: range.line });
+            }
+        }
+
+        let sorted = self
+            .ranges
+            .iter()
+            .filter(|range| range.len > 0)
+            .sorted_by_key(|range| (range.input, range.line))
+            .collect_vec();
+        for (i, range) in sorted.iter().enumerate() {
+            let end = range.input as u128 + range.len as u128;
+            for other in sorted[i + 1..]
+                .iter()
+                .take_while(|o| (o.input as u128) < end)
+            {
+                let (line, other_line) = if range.line < other.line {
+                    (range.line, other.line)
+                } else {
+                    (other.line, range.line)
+                };
+                lints.push(Lint::Overlap { line, other_line });
+            }
+        }
+
+        let mut covered_end = sorted.first().map(|range| range.input as u128);
+        for range in &sorted {
+            let end = range.input as u128 + range.len as u128;
+            if let Some(covered) = covered_end.filter(|covered| *covered < range.input as u128) {
+                lints.push(Lint::Gap {
+                    line: range.line,
+                    start: covered as u64,
+                    len: (range.input as u128 - covered) as u64,
+                });
+            }
+            covered_end = covered_end.max(Some(end));
+        }
+
+        lints.sort_by_key(|lint| lint.line());
+        lints
+    }
+
+    /// Every input that maps to `value`, including `value` itself when no range covers it.
+    fn unmap(&self, value: u64) -> Vec<u64> {
+        let mut inputs: Vec<u64> = self
+            .ranges
+            .iter()
+            .filter_map(|range| range.inverse().get(value))
+            .collect();
+        if self.ranges.iter().all(|range| range.get(value).is_none()) {
+            inputs.push(value);
+        }
+        inputs
+    }
+
+    /// Every input range that maps into `range`, including the parts of `range` itself
+    /// that no range covers.
+    fn unmap_range(&self, range: (u64, u64)) -> Vec<(u64, u64)> {
+        let mapped = self.ranges.iter().flat_map(|r| {
+            let inverse = r.inverse();
+            inverse
+                .get_overlap(range)
+                .inside
+                .into_iter()
+                .map(move |(start, len)| (inverse.get(start).unwrap(), len))
+        });
+        let identity = self.ranges.iter().fold(vec![range], |out, r| {
+            out.into_iter()
+                .flat_map(|o| r.get_overlap(o).outside)
+                .collect()
+        });
+        mapped.chain(identity).collect()
+    }
 }
 
 struct Maps(Vec<Map>);
 impl Maps {
-    fn map(&self, seed: u64) -> u64 {
-        self.0
-            .iter()
-            .map(|map| |n| map.map(n))
-            .fold(seed, |acc, f| f(acc))
+    /// Finds the shortest chain of maps leading from the `from` category to the `to` category.
+    fn path(&self, from: &str, to: &str) -> Option<Vec<&Map>> {
+        let mut previous: HashMap<&str, &Map> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(category) = queue.pop_front() {
+            if category == to {
+                let mut path = Vec::new();
+                let mut category = to;
+                while let Some(map) = previous.get(category) {
+                    path.push(*map);
+                    category = &map.source;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for map in self.0.iter().filter(|map| map.source == category) {
+                let next = map.destination.as_str();
+                if next != from && !previous.contains_key(next) {
+                    previous.insert(next, map);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
     }
 
-    fn map_range(&self, range: (u64, u64)) -> Vec<(u64, u64)> {
-        self.0.iter().fold(vec![range], |ranges, map| {
-            ranges
+    /// Converts `value` from the `from` category to the `to` category.
+    fn convert(&self, from: &str, to: &str, value: u64) -> Option<u64> {
+        self.compose(from, to).map(|composed| composed.get(value))
+    }
+
+    /// Converts `range` from the `from` category to the ranges it covers in the `to` category.
+    fn convert_range(&self, from: &str, to: &str, range: (u64, u64)) -> Option<Vec<(u64, u64)>> {
+        self.compose(from, to).map(|composed| {
+            composed
+                .get_range(range)
+                .into_iter()
+                .map(|(_, start, len)| (start, len))
+                .collect()
+        })
+    }
+
+    /// Stage-by-stage reference for [`Maps::convert`].
+    #[cfg(test)]
+    fn convert_stepwise(&self, from: &str, to: &str, value: u64) -> Option<u64> {
+        self.path(from, to)
+            .map(|path| path.iter().fold(value, |acc, map| map.map(acc)))
+    }
+
+    /// Stage-by-stage reference for [`Maps::convert_range`].
+    #[cfg(test)]
+    fn convert_range_stepwise(
+        &self,
+        from: &str,
+        to: &str,
+        range: (u64, u64),
+    ) -> Option<Vec<(u64, u64)>> {
+        self.path(from, to).map(|path| {
+            path.iter().fold(vec![range], |ranges, map| {
+                ranges
+                    .into_iter()
+                    .flat_map(|range| map.map_range(range))
+                    .collect()
+            })
+        })
+    }
+
+    fn lint(&self) -> Vec<Lint> {
+        self.0.iter().flat_map(|map| map.lint()).collect()
+    }
+
+    /// Converts `value` from `from` to `to`, recording every map it passes through.
+    fn trace(&self, from: &str, to: &str, value: u64) -> Option<Vec<Step>> {
+        self.path(from, to).map(|path| {
+            let mut value = value;
+            path.iter()
+                .map(|map| {
+                    let step = Step {
+                        source: map.source.clone(),
+                        destination: map.destination.clone(),
+                        input: value,
+                        output: map.map(value),
+                        line: map.find_range(value).map(|range| range.line),
+                    };
+                    value = step.output;
+                    step
+                })
+                .collect()
+        })
+    }
+
+    /// Collapses the chain of maps from `from` to `to` into a single function.
+    fn compose(&self, from: &str, to: &str) -> Option<Piecewise> {
+        self.path(from, to).map(|path| {
+            path.iter().fold(Piecewise::identity(), |composed, map| {
+                composed.then(&Piecewise::from_map(map))
+            })
+        })
+    }
+
+    /// Every value in the `from` category that converts to `value` in the `to` category.
+    fn convert_back(&self, from: &str, to: &str, value: u64) -> Option<Vec<u64>> {
+        self.path(from, to).map(|path| {
+            path.iter()
+                .rev()
+                .fold(vec![value], |values, map| {
+                    values
+                        .into_iter()
+                        .flat_map(|value| map.unmap(value))
+                        .collect()
+                })
                 .into_iter()
-                .flat_map(|range| map.map_range(range))
+                .sorted()
                 .collect()
         })
     }
+
+    /// Every range in the `from` category that converts into `range` in the `to` category.
+    fn convert_range_back(
+        &self,
+        from: &str,
+        to: &str,
+        range: (u64, u64),
+    ) -> Option<Vec<(u64, u64)>> {
+        self.path(from, to).map(|path| {
+            path.iter()
+                .rev()
+                .fold(vec![range], |ranges, map| {
+                    ranges
+                        .into_iter()
+                        .flat_map(|range| map.unmap_range(range))
+                        .collect()
+                })
+                .into_iter()
+                .sorted()
+                .collect()
+        })
+    }
+}
+
+/// A problem found by [`Maps::lint`]. Lines are 1-based positions in the almanac.
+#[derive(Debug, PartialEq, Eq)]
+enum Lint {
+    /// Two ranges in the same map share source values, so the result depends on their order.
+    Overlap { line: usize, other_line: usize },
+    /// A range of length 0, which maps nothing.
+    ZeroLength { line: usize },
+    /// A range whose source or destination runs past `u64::MAX`.
+    Overflow { line: usize },
+    /// Source values left unmapped between the ranges of a map, ending before `line`.
+    Gap { line: usize, start: u64, len: u64 },
+}
+impl Lint {
+    fn line(&self) -> usize {
+        match self {
+            Lint::Overlap { line, .. }
+            | Lint::ZeroLength { line }
+            | Lint::Overflow { line }
+            | Lint::Gap { line, .. } => *line,
+        }
+    }
+
+    /// Gaps are mapped to themselves, so only they are safe to solve with.
+    fn is_error(&self) -> bool {
+        !matches!(self, Lint::Gap { .. })
+    }
+}
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::Overlap { line, other_line } => write!(
+                f,
+                "line {}: source range overlaps the one on line {}",
+                other_line, line
+            ),
+            Lint::ZeroLength { line } => write!(f, "line {}: range has length 0", line),
+            Lint::Overflow { line } => write!(f, "line {}: range overflows u64", line),
+            Lint::Gap { line, start, len } => write!(
+                f,
+                "line {}: {} values from {} are not covered by any range",
+                line, len, start
+            ),
+        }
+    }
+}
+
+/// A function over `u64` made of consecutive pieces, each adding a constant offset to the
+/// values it covers. A piece starts at its breakpoint and ends where the next one starts.
+#[derive(Debug, PartialEq, Eq)]
+struct Piecewise {
+    pieces: Vec<(u64, i128)>,
+}
+impl Piecewise {
+    fn identity() -> Piecewise {
+        Piecewise {
+            pieces: vec![(0, 0)],
+        }
+    }
+
+    fn from_map(map: &Map) -> Piecewise {
+        let breakpoints = map
+            .ranges
+            .iter()
+            .flat_map(|range| [range.input as u128, range.input as u128 + range.len as u128])
+            .filter(|breakpoint| *breakpoint <= u64::MAX as u128)
+            .map(|breakpoint| breakpoint as u64)
+            .chain([0])
+            .sorted()
+            .dedup();
+
+        Piecewise::merged(breakpoints.map(|start| (start, map.map(start) as i128 - start as i128)))
+    }
+
+    /// Builds a function from sorted pieces, dropping breakpoints that don't change the offset.
+    fn merged(pieces: impl IntoIterator<Item = (u64, i128)>) -> Piecewise {
+        let mut merged: Vec<(u64, i128)> = Vec::new();
+        for (start, offset) in pieces {
+            if merged.last().map(|(_, o)| *o) != Some(offset) {
+                merged.push((start, offset));
+            }
+        }
+        Piecewise { pieces: merged }
+    }
+
+    fn end(&self, i: usize) -> u128 {
+        self.pieces
+            .get(i + 1)
+            .map(|(start, _)| *start as u128)
+            .unwrap_or(u64::MAX as u128 + 1)
+    }
+
+    fn index_of(&self, value: u64) -> usize {
+        self.pieces.partition_point(|(start, _)| *start <= value) - 1
+    }
+
+    fn get(&self, value: u64) -> u64 {
+        let (_, offset) = self.pieces[self.index_of(value)];
+        (value as i128 + offset) as u64
+    }
+
+    /// Maps `range` to output ranges, each given with the input it starts from.
+    fn get_range(&self, range: (u64, u64)) -> Vec<(u64, u64, u64)> {
+        let (start, len) = range;
+        let end = start as u128 + len as u128;
+        let mut from = start as u128;
+        let mut i = self.index_of(start);
+        let mut out = Vec::new();
+        while from < end {
+            let to = self.end(i).min(end);
+            let (_, offset) = self.pieces[i];
+            out.push((
+                from as u64,
+                (from as i128 + offset) as u64,
+                (to - from) as u64,
+            ));
+            from = to;
+            i += 1;
+        }
+        out
+    }
+
+    /// The function applying `self` and then `next`.
+    fn then(&self, next: &Piecewise) -> Piecewise {
+        let pieces = self
+            .pieces
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (start, offset))| {
+                let image_start = *start as i128 + offset;
+                let image_end = self.end(i) as i128 + offset;
+                let first = next.index_of(image_start as u64);
+                next.pieces[first..]
+                    .iter()
+                    .take_while(move |(next_start, _)| (*next_start as i128) < image_end)
+                    .map(move |(next_start, next_offset)| {
+                        let from = (*next_start as i128).max(image_start);
+                        ((from - offset) as u64, offset + next_offset)
+                    })
+            });
+        Piecewise::merged(pieces)
+    }
+}
+impl fmt::Display for Piecewise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (start, offset)) in self.pieces.iter().enumerate() {
+            writeln!(f, "{}..{}: {:+}", start, self.end(i), offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// One map applied to one value, with the line of the range that matched it.
+#[derive(Debug, PartialEq, Eq)]
+struct Step {
+    source: String,
+    destination: String,
+    input: u64,
+    output: u64,
+    line: Option<usize>,
+}
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} -> {} {}",
+            self.source, self.input, self.destination, self.output
+        )?;
+        match self.line {
+            Some(line) => write!(f, " (line {})", line),
+            None => write!(f, " (unmapped)"),
+        }
+    }
+}
+
+/// Where the lowest location of part 2 came from.
+#[derive(Debug, PartialEq, Eq)]
+struct Provenance {
+    location: u64,
+    seed_range: (u64, u64),
+    seed: u64,
+    steps: Vec<Step>,
+}
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (start, len) = self.seed_range;
+        writeln!(
+            f,
+            "Location {} comes from seed {} in range {}..{}",
+            self.location,
+            self.seed,
+            start,
+            start + len
+        )?;
+        for step in &self.steps {
+            writeln!(f, "  {}", step)?;
+        }
+        Ok(())
+    }
+}
+
+/// How the numbers on the `seeds:` line are read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SeedLayout {
+    /// Every number is a seed on its own.
+    Singles,
+    /// Numbers come in `start length` pairs, each describing a range of seeds.
+    Pairs,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseSeedsError {
+    Missing,
+    Empty,
+    OddCount(usize),
+    /// A `start length` pair with a length of 0.
+    EmptyRange {
+        start: u64,
+    },
+    InvalidNumber {
+        line: usize,
+        token: String,
+    },
+}
+impl fmt::Display for ParseSeedsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseSeedsError::Missing => write!(f, "no `seeds:` line"),
+            ParseSeedsError::Empty => write!(f, "the seed list is empty"),
+            ParseSeedsError::OddCount(count) => {
+                write!(f, "{} seed numbers can't be split into pairs", count)
+            }
+            ParseSeedsError::EmptyRange { start } => {
+                write!(f, "the seed range starting at {} is empty", start)
+            }
+            ParseSeedsError::InvalidNumber { line, token } => {
+                write!(f, "line {}: `{}` is not a seed number", line, token)
+            }
+        }
+    }
+}
+impl Error for ParseSeedsError {}
+
+#[derive(Debug, PartialEq, Eq)]
+enum AlmanacError {
+    Seeds(ParseSeedsError),
+    /// No chain of maps converts the `from` category into the `to` category.
+    NoPath {
+        from: String,
+        to: String,
+    },
+}
+impl fmt::Display for AlmanacError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlmanacError::Seeds(error) => write!(f, "{}", error),
+            AlmanacError::NoPath { from, to } => {
+                write!(f, "no maps lead from {} to {}", from, to)
+            }
+        }
+    }
+}
+impl Error for AlmanacError {}
+impl AlmanacError {
+    fn no_path(from: &str, to: &str) -> AlmanacError {
+        AlmanacError::NoPath {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
 }
 
+const START_CATEGORY: &str = "seed";
+const END_CATEGORY: &str = "location";
+
 fn main() -> std::io::Result<()> {
     let input = read_input(Day::DAY5)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let maps = parse_maps(&input);
 
-    println!("Part 1: {}", part1(&input));
-    println!("Part 2: {}", part2(&input));
+    if args.iter().any(|arg| arg == "--lint") {
+        let lints = maps.lint();
+        for lint in &lints {
+            println!("{}", lint);
+        }
+        if lints.iter().any(|lint| lint.is_error()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "almanac failed validation",
+            ));
+        }
+    }
+
+    let no_path = || invalid_data(AlmanacError::no_path(START_CATEGORY, END_CATEGORY));
+    if let Some(location) = args.iter().find_map(|arg| arg.strip_prefix("--seeds-for=")) {
+        let location = location.parse().map_err(invalid_input)?;
+        let seeds = maps
+            .convert_back(START_CATEGORY, END_CATEGORY, location)
+            .ok_or_else(no_path)?;
+        println!("Seeds for location {}: {:?}", location, seeds);
+    }
+    if let Some(window) = args.iter().find_map(|arg| arg.strip_prefix("--window=")) {
+        let (start, len) = parse_numbers(window)?
+            .into_iter()
+            .collect_tuple()
+            .ok_or_else(|| invalid_input("expected --window=START,LEN"))?;
+        let seeds = maps
+            .convert_range_back(START_CATEGORY, END_CATEGORY, (start, len))
+            .ok_or_else(no_path)?;
+        println!(
+            "Seed ranges for locations {}..{}: {:?}",
+            start,
+            start + len,
+            seeds
+        );
+    }
+
+    if let Some(query) = args.iter().find_map(|arg| arg.strip_prefix("--convert=")) {
+        const USAGE: &str = "expected --convert=FROM,TO,VALUE or --convert=FROM,TO,START,LEN";
+        let (from, to, nums) = query
+            .splitn(3, ',')
+            .collect_tuple()
+            .ok_or_else(|| invalid_input(USAGE))?;
+        let no_path = || invalid_data(AlmanacError::no_path(from, to));
+        match parse_numbers(nums)?[..] {
+            [value] => println!(
+                "{} {} is {} {}",
+                from,
+                value,
+                to,
+                maps.convert(from, to, value).ok_or_else(no_path)?
+            ),
+            [start, len] => println!(
+                "{} {}..{} is {} {:?}",
+                from,
+                start,
+                start + len,
+                to,
+                maps.convert_range(from, to, (start, len))
+                    .ok_or_else(no_path)?
+            ),
+            _ => return Err(invalid_input(USAGE)),
+        }
+    }
+
+    if args.iter().any(|arg| arg == "--dump-composed") {
+        print!("{}", compose_seed_to_location(&maps).map_err(invalid_data)?);
+    }
+
+    if args.iter().any(|arg| arg == "--explain") {
+        for (seed, _) in parse_seeds(&input, SeedLayout::Singles).map_err(invalid_data)? {
+            println!("Seed {}:", seed);
+            let steps = maps
+                .trace(START_CATEGORY, END_CATEGORY, seed)
+                .ok_or_else(no_path)?;
+            for step in steps {
+                println!("  {}", step);
+            }
+        }
+    }
+
+    println!("Part 1: {}", part1(&input).map_err(invalid_data)?);
+    let provenance = part2(&input).map_err(invalid_data)?;
+    println!("Part 2: {}", provenance.location);
+    if args.iter().any(|arg| arg == "--explain") {
+        print!("{}", provenance);
+    }
 
     Ok(())
 }
 
-fn part1(input: &String) -> u64 {
-    let seeds = parse_seeds(&input);
-    let maps = parse_maps(&input);
+fn invalid_data(error: impl Error + Send + Sync + 'static) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+}
 
-    seeds.into_iter().map(|seed| maps.map(seed)).min().unwrap()
+fn invalid_input(error: impl Into<Box<dyn Error + Send + Sync>>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, error)
 }
 
-fn part2(input: &String) -> u64 {
-    let seeds = parse_seeds(&input);
-    let maps = parse_maps(&input);
+/// Reads the comma separated numbers of a command line argument.
+fn parse_numbers(raw: &str) -> std::io::Result<Vec<u64>> {
+    raw.split(',')
+        .map(|n| n.parse().map_err(invalid_input))
+        .collect()
+}
 
-    let seed_ranges: Vec<(u64, u64)> = seeds
-        .iter()
-        .enumerate()
-        .group_by(|(i, _)| i / 2)
-        .into_iter()
-        .map(|(_, group)| group.collect::<Vec<_>>())
-        .map(|group| (*group[0].1, *group[1].1))
-        .collect();
+fn part1(input: &str) -> Result<u64, AlmanacError> {
+    let seeds = parse_seeds(input, SeedLayout::Singles).map_err(AlmanacError::Seeds)?;
+    let maps = parse_maps(input);
+    let composed = compose_seed_to_location(&maps)?;
 
-    seed_ranges
+    Ok(seeds
         .into_iter()
-        .flat_map(|range| maps.map_range(range))
-        .map(|(start, _)| start)
+        .map(|(seed, _)| composed.get(seed))
         .min()
-        .unwrap()
+        .unwrap())
 }
 
-fn parse_seeds(input: &str) -> Vec<u64> {
-    let re = Regex::new(r"seeds: ([0-9 ]+)").unwrap();
-    let captures = re.captures(&input).unwrap();
-    let nums = captures.get(1).map(|m| m.as_str()).unwrap();
+fn part2(input: &str) -> Result<Provenance, AlmanacError> {
+    let seed_ranges = parse_seeds(input, SeedLayout::Pairs).map_err(AlmanacError::Seeds)?;
+    let maps = parse_maps(input);
+    let composed = compose_seed_to_location(&maps)?;
 
-    nums.trim().split(" ").map(|n| n.parse().unwrap()).collect()
-}
+    let (seed_range, seed, location) = seed_ranges
+        .into_iter()
+        .flat_map(|range| {
+            composed
+                .get_range(range)
+                .into_iter()
+                .map(move |(seed, location, _)| (range, seed, location))
+        })
+        .min_by_key(|(_, _, location)| *location)
+        .unwrap();
 
-fn parse_maps(input: &str) -> Maps {
-    let maps = Regex::new(r"\n([0-9 ]+\n)+")
-        .unwrap()
-        .find_iter(&input)
-        .map(|m| m.as_str())
-        .map(|raw| parse_map(raw))
-        .collect();
+    Ok(Provenance {
+        location,
+        seed_range,
+        seed,
+        steps: maps.trace(START_CATEGORY, END_CATEGORY, seed).unwrap(),
+    })
+}
 
-    Maps(maps)
+fn compose_seed_to_location(maps: &Maps) -> Result<Piecewise, AlmanacError> {
+    maps.compose(START_CATEGORY, END_CATEGORY)
+        .ok_or_else(|| AlmanacError::no_path(START_CATEGORY, END_CATEGORY))
 }
 
-fn parse_map(raw: &str) -> Map {
-    let ranges = raw
-        .trim()
+/// Reads the seed list as `(start, length)` ranges, singles being ranges of length 1.
+/// The list may wrap onto following lines until a blank line or the next header.
+fn parse_seeds(input: &str, layout: SeedLayout) -> Result<Vec<(u64, u64)>, ParseSeedsError> {
+    let mut lines = input
         .lines()
         .map(|line| line.trim())
-        .map(|line| parse_range(line))
-        .collect();
+        .enumerate()
+        .skip_while(|(_, line)| !line.starts_with("seeds:"));
+    let (first, rest) = lines.next().ok_or(ParseSeedsError::Missing)?;
+    let lines = [(first, &rest["seeds:".len()..])]
+        .into_iter()
+        .chain(lines.take_while(|(_, line)| !line.is_empty() && !line.contains(':')));
+
+    let mut nums: Vec<u64> = Vec::new();
+    for (i, line) in lines {
+        for token in line.split_whitespace() {
+            let num = token.parse().map_err(|_| ParseSeedsError::InvalidNumber {
+                line: i + 1,
+                token: token.to_string(),
+            })?;
+            nums.push(num);
+        }
+    }
+
+    match layout {
+        _ if nums.is_empty() => Err(ParseSeedsError::Empty),
+        SeedLayout::Singles => Ok(nums.into_iter().map(|num| (num, 1)).collect()),
+        SeedLayout::Pairs if nums.len() % 2 == 1 => Err(ParseSeedsError::OddCount(nums.len())),
+        SeedLayout::Pairs => match nums.chunks(2).find(|pair| pair[1] == 0) {
+            Some(pair) => Err(ParseSeedsError::EmptyRange { start: pair[0] }),
+            None => Ok(nums.chunks(2).map(|pair| (pair[0], pair[1])).collect()),
+        },
+    }
+}
+
+fn parse_maps(input: &str) -> Maps {
+    let header = Regex::new(r"^([a-z]+)-to-([a-z]+) map:$").unwrap();
+    let mut maps: Vec<Map> = Vec::new();
 
-    Map { ranges }
+    for (i, line) in input.lines().map(|line| line.trim()).enumerate() {
+        if let Some(caps) = header.captures(line) {
+            maps.push(Map {
+                source: caps[1].to_string(),
+                destination: caps[2].to_string(),
+                ranges: Vec::new(),
+            });
+        } else if let Some(map) = maps.last_mut().filter(|_| !line.is_empty()) {
+            map.ranges.push(parse_range(line, i + 1));
+        }
+    }
+
+    Maps(maps)
 }
 
-fn parse_range(line: &str) -> Range {
+fn parse_range(raw: &str, line: usize) -> Range {
     let re = Regex::new(r"([0-9]+) ([0-9]+) ([0-9]+)").unwrap();
-    let caps = re.captures(line).unwrap();
+    let caps = re.captures(raw).unwrap();
 
     Range {
         input: caps.get(2).unwrap().as_str().parse().unwrap(),
         output: caps.get(1).unwrap().as_str().parse().unwrap(),
         len: caps.get(3).unwrap().as_str().parse().unwrap(),
+        line,
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{part1, part2, Overlap, Range};
+    use advent_of_code_2023::{
+        differential::compare,
+        generate::{almanac, generate, Rng},
+        Day,
+    };
+    use itertools::Itertools;
 
-    #[test]
-    fn test_example() {
-        let input = r#"
+    use crate::{
+        parse_maps, parse_seeds, part1, part2, AlmanacError, Lint, Overlap, ParseSeedsError,
+        Piecewise, Range, SeedLayout, Step, END_CATEGORY, START_CATEGORY,
+    };
+
+    const EXAMPLE: &str = r#"
             seeds: 79 14 55 13
 
             seed-to-soil map:
@@ -240,12 +895,294 @@ mod tests {
             humidity-to-location map:
             60 56 37
             56 93 4
-        "#
-        .trim()
-        .to_string();
+        "#;
+
+    #[test]
+    fn test_example() {
+        let input = EXAMPLE.trim();
+
+        assert_eq!(Ok(35), part1(input));
+        assert_eq!(46, part2(input).unwrap().location);
+    }
+
+    #[test]
+    fn test_convert() {
+        let maps = parse_maps(EXAMPLE);
+
+        assert_eq!(Some(82), maps.convert("seed", "location", 79));
+        assert_eq!(Some(78), maps.convert("soil", "humidity", 81));
+        assert_eq!(Some(81), maps.convert("seed", "seed", 81));
+        assert_eq!(None, maps.convert("location", "seed", 82));
+        assert_eq!(None, maps.convert("seed", "planet", 79));
+        assert_eq!(
+            Some(vec![(82, 3), (46, 10), (60, 1)]),
+            maps.convert_range("seed", "location", (79, 14))
+        );
+    }
+
+    #[test]
+    fn test_convert_back() {
+        let maps = parse_maps(EXAMPLE);
+
+        assert_eq!(Some(vec![79]), maps.convert_back("seed", "location", 82));
+        assert_eq!(Some(vec![81]), maps.convert_back("soil", "humidity", 78));
+        assert_eq!(None, maps.convert_back("location", "seed", 82));
+
+        for seed in 0..200 {
+            let location = maps.convert("seed", "location", seed).unwrap();
+            let seeds = maps.convert_back("seed", "location", location).unwrap();
+            assert!(
+                seeds.contains(&seed),
+                "{} -> {} -> {:?}",
+                seed,
+                location,
+                seeds
+            );
+        }
+    }
+
+    #[test]
+    fn test_convert_range_back() {
+        let maps = parse_maps(EXAMPLE);
+        let ranges = maps
+            .convert_range_back("seed", "location", (40, 30))
+            .unwrap();
+
+        for seed in 0..200 {
+            let location = maps.convert("seed", "location", seed).unwrap();
+            let in_window = (40..70).contains(&location);
+            let in_ranges = ranges
+                .iter()
+                .any(|(start, len)| (*start..start + len).contains(&seed));
+            assert_eq!(in_window, in_ranges, "seed {} -> {}", seed, location);
+        }
+    }
+
+    #[test]
+    fn test_compose() {
+        let maps = parse_maps(EXAMPLE);
+        let composed = maps.compose("seed", "location").unwrap();
+
+        for seed in 0..200 {
+            assert_eq!(
+                maps.convert_stepwise("seed", "location", seed),
+                Some(composed.get(seed)),
+                "seed {}",
+                seed
+            );
+        }
+        for range in [(79, 14), (55, 13), (0, 200), (98, 2)] {
+            let expected = maps
+                .convert_range_stepwise("seed", "location", range)
+                .unwrap()
+                .into_iter()
+                .flat_map(|(start, len)| start..start + len)
+                .sorted()
+                .collect_vec();
+            let actual = composed
+                .get_range(range)
+                .into_iter()
+                .flat_map(|(_, start, len)| start..start + len)
+                .sorted()
+                .collect_vec();
+            assert_eq!(expected, actual, "range {:?}", range);
+        }
+    }
+
+    #[test]
+    fn test_compose_merges_breakpoints() {
+        let maps = parse_maps(
+            r#"
+            a-to-b map:
+            10 0 5
+            15 5 5
+
+            b-to-c map:
+            0 10 10
+            "#,
+        );
+
+        assert_eq!(
+            Piecewise {
+                pieces: vec![(0, 0), (10, -10), (20, 0)]
+            },
+            maps.compose("a", "c").unwrap()
+        );
+        assert_eq!(
+            Piecewise {
+                pieces: vec![(0, 10), (10, 0)]
+            },
+            maps.compose("a", "b").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_provenance() {
+        let provenance = part2(EXAMPLE.trim()).unwrap();
+
+        assert_eq!(46, provenance.location);
+        assert_eq!((79, 14), provenance.seed_range);
+        assert_eq!(82, provenance.seed);
+        assert_eq!(
+            vec![Some(5), None, None, Some(20), Some(23), Some(29), None],
+            provenance.steps.iter().map(|step| step.line).collect_vec()
+        );
+        assert_eq!(
+            Step {
+                source: "humidity".to_string(),
+                destination: "location".to_string(),
+                input: 46,
+                output: 46,
+                line: None,
+            },
+            provenance.steps[6]
+        );
+    }
+
+    #[test]
+    fn test_parse_seeds() {
+        let input = "seeds: 79 14
+            55 13
+
+            seed-to-soil map:
+            50 98 2";
+
+        assert_eq!(
+            Ok(vec![(79, 1), (14, 1), (55, 1), (13, 1)]),
+            parse_seeds(input, SeedLayout::Singles)
+        );
+        assert_eq!(
+            Ok(vec![(79, 14), (55, 13)]),
+            parse_seeds(input, SeedLayout::Pairs)
+        );
+    }
+
+    #[test]
+    fn test_parse_seeds_errors() {
+        assert_eq!(
+            Err(ParseSeedsError::Missing),
+            parse_seeds("seed-to-soil map:\n50 98 2", SeedLayout::Singles)
+        );
+        assert_eq!(
+            Err(ParseSeedsError::Empty),
+            parse_seeds("seeds:\n\nseed-to-soil map:", SeedLayout::Pairs)
+        );
+        assert_eq!(
+            Err(ParseSeedsError::OddCount(3)),
+            parse_seeds("seeds: 79 14 55", SeedLayout::Pairs)
+        );
+        assert_eq!(
+            Ok(vec![(79, 1), (14, 1), (55, 1)]),
+            parse_seeds("seeds: 79 14 55", SeedLayout::Singles)
+        );
+        assert_eq!(
+            Err(ParseSeedsError::InvalidNumber {
+                line: 2,
+                token: "x3".to_string()
+            }),
+            parse_seeds("seeds: 79 14\n55 x3", SeedLayout::Pairs)
+        );
+        assert_eq!(
+            Err(ParseSeedsError::EmptyRange { start: 5 }),
+            parse_seeds("seeds: 79 14 5 0", SeedLayout::Pairs)
+        );
+        assert_eq!(
+            Err(AlmanacError::Seeds(ParseSeedsError::EmptyRange {
+                start: 5
+            })),
+            part2(&EXAMPLE.replace("seeds: 79 14 55 13", "seeds: 5 0"))
+        );
+    }
+
+    #[test]
+    fn test_no_path() {
+        let input = "seeds: 1 2\n\nseed-to-soil map:\n1 2 3";
+        let no_path = AlmanacError::NoPath {
+            from: "seed".to_string(),
+            to: "location".to_string(),
+        };
+
+        assert_eq!(Err(no_path), part1(input));
+        assert_eq!(
+            "no maps lead from seed to location",
+            part2(input).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_lint() {
+        let input = "a-to-b map:
+10 0 5
+0 3 4
+7 20 0
+0 18446744073709551615 2
+50 10 5";
+
+        assert_eq!(
+            vec![
+                Lint::Overlap {
+                    line: 2,
+                    other_line: 3
+                },
+                Lint::ZeroLength { line: 4 },
+                Lint::Overflow { line: 5 },
+                Lint::Gap {
+                    line: 5,
+                    start: 15,
+                    len: 18446744073709551600
+                },
+                Lint::Gap {
+                    line: 6,
+                    start: 7,
+                    len: 3
+                },
+            ],
+            parse_maps(input).lint()
+        );
+        assert_eq!(Vec::<Lint>::new(), parse_maps(EXAMPLE).lint());
+    }
+
+    #[test]
+    fn test_convert_out_of_order() {
+        let input = r#"
+            seeds: 79
+
+            light-to-temperature map:
+            45 77 23
+            81 45 19
+            68 64 13
+
+            seed-to-soil map:
+            50 98 2
+            52 50 48
+
+            humidity-to-location map:
+            60 56 37
+            56 93 4
+
+            soil-to-fertilizer map:
+            0 15 37
+            37 52 2
+            39 0 15
+
+            temperature-to-humidity map:
+            0 69 1
+            1 0 69
+
+            water-to-light map:
+            88 18 7
+            18 25 70
 
-        assert_eq!(35, part1(&input));
-        assert_eq!(46, part2(&input));
+            fertilizer-to-water map:
+            49 53 8
+            0 11 42
+            42 0 7
+            57 7 4
+        "#;
+        let maps = parse_maps(input);
+
+        assert_eq!(Some(82), maps.convert("seed", "location", 79));
+        assert_eq!(Some(74), maps.convert("water", "light", 81));
     }
 
     #[test]
@@ -254,6 +1191,7 @@ mod tests {
             input: 3,
             output: 5,
             len: 2,
+            line: 1,
         };
 
         assert_eq!(
@@ -285,4 +1223,96 @@ mod tests {
             range.get_overlap((1, 2))
         );
     }
+
+    #[test]
+    fn test_generated() {
+        for seed in 0..5 {
+            let input = generate(Day::DAY5, seed, 10);
+
+            assert!(!parse_maps(&input).lint().iter().any(Lint::is_error));
+            assert_eq!(
+                Ok(10),
+                parse_seeds(&input, SeedLayout::Pairs).map(|seeds| seeds.len())
+            );
+            assert!(part1(&input).is_ok());
+            assert!(part2(&input).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_differential() {
+        let reference = |input: &str| {
+            let maps = parse_maps(input);
+            parse_seeds(input, SeedLayout::Pairs)
+                .ok()?
+                .into_iter()
+                .flat_map(|(start, len)| start..start + len)
+                .map(|seed| maps.convert_stepwise(START_CATEGORY, END_CATEGORY, seed))
+                .collect::<Option<Vec<_>>>()?
+                .into_iter()
+                .min()
+        };
+        let optimized = |input: &str| part2(input).ok().map(|provenance| provenance.location);
+
+        compare(
+            0..5,
+            |seed| almanac(&mut Rng::new(seed), 4, 200),
+            reference,
+            optimized,
+        )
+        .unwrap_or_else(|disagreement| panic!("{}", disagreement));
+    }
+
+    #[test]
+    fn test_overlap_partitions_range() {
+        let mut rng = Rng::new(5);
+        for _ in 0..10_000 {
+            let range = Range {
+                input: rng.below(50),
+                output: rng.below(50),
+                len: rng.between(1, 20),
+                line: 1,
+            };
+            let (start, len) = (rng.below(80), rng.between(1, 30));
+            let overlap = range.get_overlap((start, len));
+            let pieces = [&overlap.inside[..], &overlap.outside[..]]
+                .concat()
+                .into_iter()
+                .sorted()
+                .collect_vec();
+
+            assert!(pieces.iter().all(|&(_, len)| len > 0));
+            assert_eq!(start, pieces[0].0);
+            assert!(pieces.iter().tuple_windows().all(|(a, b)| a.0 + a.1 == b.0));
+            assert_eq!(start + len, pieces.last().map(|(s, l)| s + l).unwrap());
+            assert!(overlap
+                .inside
+                .iter()
+                .all(|&(s, l)| range.input <= s && s + l <= range.input + range.len));
+            assert!(overlap
+                .outside
+                .iter()
+                .all(|&(s, l)| s + l <= range.input || range.input + range.len <= s));
+        }
+    }
+
+    #[test]
+    fn test_get_range_preserves_length() {
+        let mut rng = Rng::new(6);
+        for _ in 0..20 {
+            let maps = parse_maps(&almanac(&mut rng, 4, 200));
+            let composed = maps.compose(START_CATEGORY, END_CATEGORY).unwrap();
+            let functions = maps.0.iter().map(Piecewise::from_map).chain([composed]);
+            for function in functions {
+                for _ in 0..50 {
+                    let range = (rng.below(200), rng.between(1, 100));
+                    let pieces = function.get_range(range);
+
+                    assert_eq!(range.0, pieces[0].0);
+                    assert!(pieces.iter().tuple_windows().all(|(a, b)| a.0 + a.2 == b.0));
+                    assert_eq!(range.1, pieces.iter().map(|(_, _, len)| len).sum::<u64>());
+                }
+            }
+        }
+    }
 }
@@ -19,13 +19,13 @@ fn main() -> std::io::Result<()> {
 fn part1(input: &str) -> u64 {
     let races = parse_races(input);
 
-    races.iter().map(|race| count_winning_moves(race)).product()
+    races.iter().map(count_winning_moves).product()
 }
 
 fn part2(input: &str) -> u64 {
     let race = parse_race(input);
 
-    count_winning_moves(&race)
+    count_winning_moves_closed_form(&race)
 }
 
 fn count_winning_moves(race: &Race) -> u64 {
@@ -41,6 +41,31 @@ fn count_winning_moves(race: &Race) -> u64 {
         .unwrap()
 }
 
+/// Counts the winning hold times without trying each one. Holding for `x`
+/// travels `x * (time - x)`, which beats the record between the roots of
+/// `x^2 - time * x + distance`.
+fn count_winning_moves_closed_form(race: &Race) -> u64 {
+    let (time, distance) = (race.time as u128, race.distance as u128);
+    let wins = |hold: u128| hold * (time - hold) > distance;
+    let Some(discriminant) = (time * time).checked_sub(4 * distance) else {
+        return 0;
+    };
+    // The float root can be a step off either way, so walk to the exact bound.
+    let root = (discriminant as f64).sqrt() as u128;
+    let mut low = (time - root.min(time)) / 2;
+    while low <= time / 2 && !wins(low) {
+        low += 1;
+    }
+    while low > 0 && wins(low - 1) {
+        low -= 1;
+    }
+    if low > time / 2 {
+        0
+    } else {
+        (time - 2 * low + 1) as u64
+    }
+}
+
 fn parse_race(input: &str) -> Race {
     let (times, distances) = input.lines().collect_tuple().unwrap();
     let times = tokenize(times);
@@ -59,7 +84,7 @@ fn parse_races(input: &str) -> Vec<Race> {
 
     times
         .into_iter()
-        .zip(distances.into_iter())
+        .zip(distances)
         .map(|(time, distance)| Race { time, distance })
         .collect()
 }
@@ -78,3 +103,69 @@ fn split_line(line: &str) -> Vec<u64> {
         .map(|raw| raw.parse().unwrap())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use advent_of_code_2023::{
+        differential::compare,
+        generate::{day6, Rng},
+    };
+
+    use crate::{
+        count_winning_moves, count_winning_moves_closed_form, parse_race, parse_races, part1,
+        part2, Race,
+    };
+
+    #[test]
+    fn test_example() {
+        let input = r#"
+            Time:      7  15   30
+            Distance:  9  40  200
+        "#
+        .trim();
+
+        assert_eq!(288, part1(input));
+        assert_eq!(71503, part2(input));
+    }
+
+    #[test]
+    fn test_closed_form() {
+        for time in 0..40 {
+            for distance in 0..time * time / 4 + 2 {
+                let race = Race { time, distance };
+                assert_eq!(
+                    count_winning_moves(&race),
+                    count_winning_moves_closed_form(&race)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_differential() {
+        let races = |input: &str| (input.lines().count() == 2).then(|| parse_races(input));
+        let race = |input: &str| (input.lines().count() == 2).then(|| parse_race(input));
+
+        compare(
+            0..50,
+            |seed| day6(&mut Rng::new(seed), 4),
+            |input| races(input).map(|races| races.iter().map(count_winning_moves).collect()),
+            |input| {
+                races(input).map(|races| {
+                    races
+                        .iter()
+                        .map(count_winning_moves_closed_form)
+                        .collect::<Vec<_>>()
+                })
+            },
+        )
+        .unwrap_or_else(|disagreement| panic!("{}", disagreement));
+        compare(
+            0..10,
+            |seed| day6(&mut Rng::new(seed), 3),
+            |input| race(input).map(|race| count_winning_moves(&race)),
+            |input| race(input).map(|race| count_winning_moves_closed_form(&race)),
+        )
+        .unwrap_or_else(|disagreement| panic!("{}", disagreement));
+    }
+}
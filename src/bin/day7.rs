@@ -1,11 +1,20 @@
-use std::{cmp::Ordering, convert::identity, hash::Hash, iter::zip, str::FromStr};
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    convert::identity,
+    fmt,
+    hash::Hash,
+    iter::zip,
+    num::{IntErrorKind, ParseIntError},
+    str::FromStr,
+};
 
 use advent_of_code_2023::{read_input, Day};
 use itertools::Itertools;
-use regex::Regex;
-use strum::{Display, EnumString};
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
 
-#[derive(EnumString, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Display)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(EnumString, EnumIter, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Display, Debug)]
 enum Card {
     #[strum(serialize = "2")]
     TWO,
@@ -35,34 +44,152 @@ enum Card {
     ACE,
 }
 
-impl Card {
-    fn cmp_part1(&self, other: &Self) -> Ordering {
-        self.cmp(&other)
+/// The rank order of cards, lowest first, and which cards are wild when typing a hand.
+struct RuleSet {
+    order: Vec<Card>,
+    wild: Vec<Card>,
+}
+
+impl RuleSet {
+    /// Part 1: cards rank by face value and nothing is wild.
+    fn standard() -> RuleSet {
+        RuleSet {
+            order: Card::iter().collect(),
+            wild: vec![],
+        }
     }
 
-    fn cmp_part2(&self, other: &Self) -> Ordering {
-        match (&self, &other) {
-            (Card::JACK, Card::JACK) => Ordering::Equal,
-            (Card::JACK, _) => Ordering::Less,
-            (_, Card::JACK) => Ordering::Greater,
-            (_, _) => self.cmp(&other),
+    /// Part 2: jacks are jokers, wild and ranked below every other card.
+    fn jokers() -> RuleSet {
+        RuleSet {
+            order: [Card::JACK]
+                .into_iter()
+                .chain(Card::iter().filter(|card| card != &Card::JACK))
+                .collect(),
+            wild: vec![Card::JACK],
         }
     }
+
+    fn rank(&self, card: &Card) -> usize {
+        self.order.iter().position(|c| c == card).unwrap()
+    }
+
+    fn cmp_cards(&self, c1: &Card, c2: &Card) -> Ordering {
+        self.rank(c1).cmp(&self.rank(c2))
+    }
+
+    fn hand_type(&self, cards: &Cards) -> HandType {
+        HandType(cards.get_signature(&self.wild))
+    }
+
+    fn cmp_hands(&self, h1: &Hand, h2: &Hand) -> Ordering {
+        h1.cmp(
+            h2,
+            |c1, c2| self.cmp_cards(c1, c2),
+            |cards| self.hand_type(cards),
+        )
+    }
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
-enum HandType {
-    HighCard,
-    Pair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
+/// A hand's category, given by its count signature: the sizes of its groups of equal
+/// cards, largest first. Categories compare by signature, so for 5 cards five of a kind
+/// `[5]` beats four of a kind `[4, 1]`, which beats a full house `[3, 2]` and so on.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+struct HandType(Vec<usize>);
+
+impl fmt::Display for HandType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let groups = self
+            .0
+            .iter()
+            .filter(|size| **size > 1)
+            .dedup_with_count()
+            .map(|(count, size)| match (*size, count) {
+                (2, 1) => "pair".to_string(),
+                (2, count) => format!("{} pair", number_name(count)),
+                (3, 1) => "three of a kind".to_string(),
+                (3, count) => format!("{} triples", number_name(count)),
+                (size, 1) => format!("{} of a kind", number_name(size)),
+                (size, count) => format!("{} {}s of a kind", number_name(count), number_name(size)),
+            })
+            .collect_vec();
+        match (self.0.as_slice(), groups.is_empty()) {
+            ([3, 2], _) => f.write_str("full house"),
+            (_, true) => f.write_str("high card"),
+            (_, false) => f.write_str(&groups.join(" and ")),
+        }
+    }
 }
 
-#[derive(Debug)]
-struct ParseCardsError;
+fn number_name(n: usize) -> String {
+    const NAMES: [&str; 11] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    ];
+    NAMES
+        .get(n)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| n.to_string())
+}
+
+/// A character in a hand that isn't a card, at its 1-based position in the hand.
+#[derive(Debug, PartialEq, Eq)]
+struct ParseCardsError {
+    character: char,
+    position: usize,
+}
+impl fmt::Display for ParseCardsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' at position {} is not a card",
+            self.character, self.position
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseHandErrorKind {
+    /// The line isn't a hand and a bid separated by whitespace.
+    Format,
+    Card(ParseCardsError),
+    Length {
+        expected: usize,
+        actual: usize,
+    },
+    InvalidBid(String),
+    BidOverflow(String),
+}
+
+/// A line of the input that couldn't be read as a hand, with its 1-based line number.
+#[derive(Debug, PartialEq, Eq)]
+struct ParseHandError {
+    line: usize,
+    kind: ParseHandErrorKind,
+}
+impl fmt::Display for ParseHandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ParseHandErrorKind::Format => f.write_str("expected a hand and a bid"),
+            ParseHandErrorKind::Card(error) => write!(f, "{}", error),
+            ParseHandErrorKind::Length { expected, actual } => {
+                write!(f, "expected {} cards, found {}", expected, actual)
+            }
+            ParseHandErrorKind::InvalidBid(raw) => write!(f, "'{}' is not a bid", raw),
+            ParseHandErrorKind::BidOverflow(raw) => write!(f, "bid {} is too large", raw),
+        }
+    }
+}
+impl std::error::Error for ParseHandError {}
+
+#[derive(Default)]
+struct ParseOptions {
+    /// Accept lowercase cards, like `kt9jj`.
+    ignore_case: bool,
+    /// The number of cards every hand must have. When unset, every hand must have
+    /// as many cards as the first.
+    hand_size: Option<usize>,
+}
 
 #[derive(Clone)]
 struct Cards(Vec<Card>);
@@ -75,10 +202,7 @@ impl FromStr for Cards {
     type Err = ParseCardsError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.chars()
-            .map(|c| Card::from_str(&c.to_string()))
-            .collect::<Result<Cards, _>>()
-            .map_err(|_| ParseCardsError)
+        Cards::parse(s, false)
     }
 }
 impl std::fmt::Display for Cards {
@@ -87,6 +211,23 @@ impl std::fmt::Display for Cards {
     }
 }
 impl Cards {
+    fn parse(s: &str, ignore_case: bool) -> Result<Cards, ParseCardsError> {
+        s.chars()
+            .zip(1..)
+            .map(|(character, position)| {
+                let c = if ignore_case {
+                    character.to_ascii_uppercase()
+                } else {
+                    character
+                };
+                Card::from_str(&c.to_string()).map_err(|_| ParseCardsError {
+                    character,
+                    position,
+                })
+            })
+            .collect()
+    }
+
     fn cmp(&self, other: &Self, card_cmp: impl Fn(&Card, &Card) -> Ordering) -> Ordering {
         for (c1, c2) in self.0.iter().zip(other.0.iter()) {
             match card_cmp(c1, c2) {
@@ -97,185 +238,372 @@ impl Cards {
         }
         Ordering::Equal
     }
-    fn compute_hand_type_part1(&self) -> HandType {
-        if self.is_five_of_a_kind() {
-            HandType::FiveOfAKind
-        } else if self.is_four_of_a_kind() {
-            HandType::FourOfAKind
-        } else if self.is_full_house() {
-            HandType::FullHouse
-        } else if self.is_three_of_a_kind() {
-            HandType::ThreeOfAKind
-        } else if self.is_two_pair() {
-            HandType::TwoPair
-        } else if self.is_pair() {
-            HandType::Pair
-        } else {
-            HandType::HighCard
+    /// The sizes of the groups of equal cards, largest first, with every wild card
+    /// joining the largest group.
+    fn get_signature(&self, wild: &[Card]) -> Vec<usize> {
+        let wild_count = self.0.iter().filter(|card| wild.contains(card)).count();
+        let mut counts: Vec<usize> = self
+            .0
+            .iter()
+            .filter(|card| !wild.contains(card))
+            .counts_by(identity)
+            .into_values()
+            .sorted()
+            .rev()
+            .collect();
+        match counts.first_mut() {
+            Some(largest) => *largest += wild_count,
+            None => counts.push(wild_count),
         }
+        counts
     }
+}
 
-    fn compute_hand_type_part2(&self) -> HandType {
-        if self.is_five_of_a_kind_j() {
-            HandType::FiveOfAKind
-        } else if self.is_four_of_a_kind_j() {
-            HandType::FourOfAKind
-        } else if self.is_full_house_j() {
-            HandType::FullHouse
-        } else if self.is_three_of_a_kind_j() {
-            HandType::ThreeOfAKind
-        } else if self.is_two_pair_j() {
-            HandType::TwoPair
-        } else if self.is_pair_j() {
-            HandType::Pair
-        } else {
-            HandType::HighCard
-        }
+#[derive(Clone)]
+struct Hand {
+    cards: Cards,
+    bid: u32,
+}
+impl Hand {
+    fn cmp(
+        &self,
+        other: &Self,
+        card_cmp: impl Fn(&Card, &Card) -> Ordering,
+        hand_type_fn: impl Fn(&Cards) -> HandType,
+    ) -> Ordering {
+        hand_type_fn(&self.cards)
+            .cmp(&hand_type_fn(&other.cards))
+            .then(self.cards.cmp(&other.cards, card_cmp))
     }
+}
 
-    fn is_five_of_a_kind(&self) -> bool {
-        self.0.iter().unique().count() == 1
-    }
+/// How hands are dealt from a standard deck holding each of the 13 cards in 4 suits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Dealing {
+    /// Every card is drawn from a full deck, so hands are ordered sequences of `size`
+    /// cards over the 13 ranks.
+    WithReplacement,
+    /// Cards are drawn from one 52-card deck, so hands are unordered and hold at most
+    /// 4 of each card.
+    WithoutReplacement,
+}
 
-    fn is_four_of_a_kind(&self) -> bool {
-        self.get_counts().contains(&4)
-    }
+const SUITS: u128 = 4;
 
-    fn is_full_house(&self) -> bool {
-        self.is_three_of_a_kind() && self.is_pair()
+/// The exact number of hands of each type over every deal of `size` cards, and the
+/// total number of deals.
+fn count_hand_types(
+    rules: &RuleSet,
+    size: usize,
+    dealing: Dealing,
+) -> (BTreeMap<HandType, u128>, u128) {
+    let mut counts: BTreeMap<HandType, u128> = BTreeMap::new();
+    for hand in Card::iter().combinations_with_replacement(size) {
+        let groups = hand
+            .iter()
+            .dedup_with_count()
+            .map(|(count, _)| count as u128);
+        let weight: u128 = match dealing {
+            Dealing::WithReplacement => {
+                factorial(size as u128) / groups.map(factorial).product::<u128>()
+            }
+            Dealing::WithoutReplacement => groups.map(|count| binomial(SUITS, count)).product(),
+        };
+        if weight > 0 {
+            let hand_type = rules.hand_type(&hand.into_iter().collect());
+            *counts.entry(hand_type).or_default() += weight;
+        }
     }
+    let total = counts.values().sum();
+    (counts, total)
+}
 
-    fn is_three_of_a_kind(&self) -> bool {
-        self.get_counts().contains(&3)
-    }
+fn factorial(n: u128) -> u128 {
+    (1..=n).product()
+}
 
-    fn is_two_pair(&self) -> bool {
-        self.get_counts().into_iter().filter(|c| c == &2).count() == 2
+fn binomial(n: u128, k: u128) -> u128 {
+    if k > n {
+        0
+    } else {
+        (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
     }
+}
 
-    fn is_pair(&self) -> bool {
-        self.get_counts().into_iter().filter(|c| c == &2).count() == 1
-    }
+/// Every ordered hand of `size` cards that has the given type.
+fn hands_of_type<'a>(
+    rules: &'a RuleSet,
+    size: usize,
+    hand_type: &'a HandType,
+) -> impl Iterator<Item = Cards> + 'a {
+    (0..size)
+        .map(|_| Card::iter())
+        .multi_cartesian_product()
+        .map(Cards)
+        .filter(move |cards| &rules.hand_type(cards) == hand_type)
+}
 
-    fn is_five_of_a_kind_j(&self) -> bool {
-        let joker_count = self.get_joker_count();
-        joker_count == 5 || self.get_counts().contains(&(5 - self.get_joker_count()))
-    }
+fn main() -> std::io::Result<()> {
+    let input = read_input(Day::DAY7)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let rules = if args.iter().any(|arg| arg == "--jokers") {
+        RuleSet::jokers()
+    } else {
+        RuleSet::standard()
+    };
 
-    fn is_four_of_a_kind_j(&self) -> bool {
-        let joker_count = self.get_joker_count();
-        self.get_counts().contains(&(4 - self.get_joker_count()))
-            && !(self.is_pair() && joker_count == 2)
-    }
+    let invalid_input = |error| std::io::Error::new(std::io::ErrorKind::InvalidInput, error);
 
-    fn is_full_house_j(&self) -> bool {
-        (self.is_two_pair() && self.0.contains(&Card::JACK)) | self.is_full_house()
+    if let Some(size) = args.iter().find_map(|arg| arg.strip_prefix("--odds=")) {
+        let size = size.parse().map_err(invalid_input)?;
+        for dealing in [Dealing::WithReplacement, Dealing::WithoutReplacement] {
+            let (counts, total) = count_hand_types(&rules, size, dealing);
+            println!("{:?}:", dealing);
+            for (hand_type, count) in counts.iter().rev() {
+                let probability = *count as f64 / total as f64;
+                println!("  {}: {}/{} ({:.6})", hand_type, count, total, probability);
+            }
+        }
+        return Ok(());
     }
 
-    fn is_three_of_a_kind_j(&self) -> bool {
-        self.get_counts().contains(&(3 - self.get_joker_count()))
-    }
+    let options = ParseOptions {
+        ignore_case: args.iter().any(|arg| arg == "--ignore-case"),
+        hand_size: args
+            .iter()
+            .find_map(|arg| arg.strip_prefix("--hand-size="))
+            .map(|size| size.parse().map_err(invalid_input))
+            .transpose()?,
+    };
+    let invalid_data = |error| std::io::Error::new(std::io::ErrorKind::InvalidData, error);
 
-    fn is_two_pair_j(&self) -> bool {
-        self.get_counts().into_iter().filter(|c| c == &2).count() == 2
+    if let Some(format) = args.iter().find_map(|arg| arg.strip_prefix("--standings=")) {
+        let hands = parse_hands(&input, &options).map_err(invalid_data)?;
+        let standings = standings(hands, &rules);
+        match format {
+            "csv" => print!("{}", render_standings_csv(&standings)),
+            _ => print!("{}", render_standings_text(&standings)),
+        }
     }
 
-    fn is_pair_j(&self) -> bool {
-        self.0.contains(&Card::JACK) | self.is_pair()
+    if let Some(signature) = args.iter().find_map(|arg| arg.strip_prefix("--enumerate=")) {
+        let mut signature: Vec<usize> = signature
+            .split(',')
+            .map(|n| n.parse().map_err(invalid_input))
+            .collect::<Result<_, _>>()?;
+        if signature.contains(&0) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "a hand type can't have a group of 0 cards",
+            ));
+        }
+        // Signatures list the largest group first, whatever order they are given in.
+        signature.sort_by(|a, b| b.cmp(a));
+        let size = signature.iter().sum();
+        let hand_type = HandType(signature);
+        for cards in hands_of_type(&rules, size, &hand_type) {
+            println!("{}", cards);
+        }
+        return Ok(());
     }
 
-    fn get_joker_count(&self) -> usize {
-        self.0
-            .iter()
-            .counts_by(identity)
-            .get(&Card::JACK)
-            .map(|v| *v)
-            .unwrap_or(0)
-    }
+    println!("Part 1: {}", part1(&input, &options).map_err(invalid_data)?);
+    println!("Part 2: {}", part2(&input, &options).map_err(invalid_data)?);
 
-    fn get_counts(&self) -> Vec<usize> {
-        self.0.iter().counts_by(identity).into_values().collect()
-    }
+    Ok(())
 }
 
-#[derive(Clone)]
-struct Hand {
-    cards: Cards,
-    bid: u32,
+fn part1(input: &str, options: &ParseOptions) -> Result<u64, ParseHandError> {
+    let hands = parse_hands(input, options)?;
+    Ok(compute_rank_product_sum(hands, &RuleSet::standard()))
 }
-impl Hand {
-    fn cmp(
-        &self,
-        other: &Self,
-        card_cmp: impl Fn(&Card, &Card) -> Ordering,
-        hand_type_fn: impl Fn(&Cards) -> HandType,
-    ) -> Ordering {
-        hand_type_fn(&self.cards)
-            .cmp(&hand_type_fn(&other.cards))
-            .then(self.cards.cmp(&other.cards, card_cmp))
-    }
+
+fn part2(input: &str, options: &ParseOptions) -> Result<u64, ParseHandError> {
+    let hands = parse_hands(input, options)?;
+    Ok(compute_rank_product_sum(hands, &RuleSet::jokers()))
 }
 
-fn main() -> std::io::Result<()> {
-    let input = read_input(Day::DAY7)?;
+fn compute_rank_product_sum(hands: Vec<Hand>, rules: &RuleSet) -> u64 {
+    standings(hands, rules)
+        .iter()
+        .map(|standing| standing.winnings)
+        .sum()
+}
 
-    println!("Part 1: {}", part1(&input));
-    println!("Part 2: {}", part2(&input));
+/// Why a hand ranks above the one just below it.
+#[derive(Debug, PartialEq, Eq)]
+enum TieBreak {
+    /// The lowest ranked hand has nothing below it.
+    Lowest,
+    /// It has a better hand type.
+    HandType,
+    /// Same hand type, and it has the better card at this 1-based position.
+    Card(usize),
+    /// Same hand type and cards, so the sort order decided.
+    Equal,
+}
+impl fmt::Display for TieBreak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TieBreak::Lowest => f.write_str("-"),
+            TieBreak::HandType => f.write_str("hand type"),
+            TieBreak::Card(position) => write!(f, "card {}", position),
+            TieBreak::Equal => f.write_str("equal"),
+        }
+    }
+}
 
-    Ok(())
+struct Standing {
+    cards: Cards,
+    hand_type: HandType,
+    tie_break: TieBreak,
+    bid: u32,
+    rank: u64,
+    /// The bid times the rank, which a `u32` bid can overflow in a `u32`.
+    winnings: u64,
 }
 
-fn part1(input: &str) -> u32 {
-    compute_rank_product_sum(input, |h1, h2| {
-        h1.cmp(
-            h2,
-            |c1, c2| c1.cmp_part1(c2),
-            Cards::compute_hand_type_part1,
-        )
-    })
+fn standings(hands: Vec<Hand>, rules: &RuleSet) -> Vec<Standing> {
+    let hands = hands
+        .into_iter()
+        .sorted_by(|h1, h2| rules.cmp_hands(h1, h2))
+        .collect_vec();
+
+    hands
+        .iter()
+        .enumerate()
+        .map(|(i, hand)| {
+            let hand_type = rules.hand_type(&hand.cards);
+            let tie_break = match i.checked_sub(1).map(|j| &hands[j]) {
+                None => TieBreak::Lowest,
+                Some(below) if rules.hand_type(&below.cards) != hand_type => TieBreak::HandType,
+                Some(below) => zip(&hand.cards.0, &below.cards.0)
+                    .position(|(c1, c2)| rules.cmp_cards(c1, c2) != Ordering::Equal)
+                    .map(|position| TieBreak::Card(position + 1))
+                    .unwrap_or(TieBreak::Equal),
+            };
+            let rank = i as u64 + 1;
+            Standing {
+                cards: hand.cards.clone(),
+                hand_type,
+                tie_break,
+                bid: hand.bid,
+                rank,
+                winnings: hand.bid as u64 * rank,
+            }
+        })
+        .collect()
 }
 
-fn part2(input: &str) -> u32 {
-    compute_rank_product_sum(input, |h1, h2| {
-        h1.cmp(
-            h2,
-            |c1, c2| c1.cmp_part2(c2),
-            Cards::compute_hand_type_part2,
-        )
-    })
+fn render_standings_text(standings: &[Standing]) -> String {
+    let rows = standings
+        .iter()
+        .map(|s| {
+            [
+                s.rank.to_string(),
+                s.cards.to_string(),
+                s.hand_type.to_string(),
+                s.tie_break.to_string(),
+                s.bid.to_string(),
+                s.winnings.to_string(),
+            ]
+        })
+        .collect_vec();
+    let header = ["rank", "hand", "type", "beats below by", "bid", "winnings"].map(String::from);
+    let widths = (0..header.len())
+        .map(|col| {
+            [&header]
+                .into_iter()
+                .chain(&rows)
+                .map(|row| row[col].len())
+                .max()
+                .unwrap()
+        })
+        .collect_vec();
+
+    [&header]
+        .into_iter()
+        .chain(&rows)
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .map(|line| line + "\n")
+        .collect()
 }
 
-fn compute_rank_product_sum(input: &str, cmp: impl Fn(&Hand, &Hand) -> Ordering) -> u32 {
-    parse_hands(input)
+fn render_standings_csv(standings: &[Standing]) -> String {
+    let rows = standings.iter().map(|s| {
+        format!(
+            "{},{},{},{},{},{}\n",
+            s.rank, s.cards, s.hand_type, s.tie_break, s.bid, s.winnings
+        )
+    });
+    ["rank,hand,type,beats_below_by,bid,winnings\n".to_string()]
         .into_iter()
-        .sorted_by(cmp)
-        .zip(0u32..)
-        .map(|(hand, i)| hand.bid * (i + 1))
-        .sum()
+        .chain(rows)
+        .collect()
 }
 
-fn parse_hands(input: &str) -> Vec<Hand> {
-    let re = Regex::new(r"([0-9TJQKA]{5}) ([0-9]+)").unwrap();
+fn parse_hands(input: &str, options: &ParseOptions) -> Result<Vec<Hand>, ParseHandError> {
+    let mut hand_size = options.hand_size;
     input
         .lines()
-        .map(|line| re.captures(line).unwrap())
-        .map(|cap| (cap.get(1).unwrap().as_str(), cap.get(2).unwrap().as_str()))
-        .map(|(raw_hand, raw_bid)| parse_hand(raw_hand, raw_bid))
+        .zip(1..)
+        .filter(|(line, _)| !line.trim().is_empty())
+        .map(|(line, i)| {
+            let hand = parse_hand(line, options.ignore_case, hand_size)
+                .map_err(|kind| ParseHandError { line: i, kind })?;
+            hand_size.get_or_insert(hand.cards.0.len());
+            Ok(hand)
+        })
         .collect()
 }
 
-fn parse_hand(raw_hand: &str, raw_bid: &str) -> Hand {
-    let cards = Cards::from_str(raw_hand).unwrap();
-    let bid = raw_bid.parse().unwrap();
+fn parse_hand(
+    line: &str,
+    ignore_case: bool,
+    hand_size: Option<usize>,
+) -> Result<Hand, ParseHandErrorKind> {
+    let (raw_hand, raw_bid) = line
+        .split_whitespace()
+        .collect_tuple()
+        .ok_or(ParseHandErrorKind::Format)?;
+
+    let cards = Cards::parse(raw_hand, ignore_case).map_err(ParseHandErrorKind::Card)?;
+    let expected = hand_size.unwrap_or(cards.0.len());
+    if cards.0.len() != expected {
+        return Err(ParseHandErrorKind::Length {
+            expected,
+            actual: cards.0.len(),
+        });
+    }
+
+    let bid = raw_bid.parse().map_err(|e: ParseIntError| match e.kind() {
+        IntErrorKind::PosOverflow => ParseHandErrorKind::BidOverflow(raw_bid.to_string()),
+        _ => ParseHandErrorKind::InvalidBid(raw_bid.to_string()),
+    })?;
 
-    Hand { cards, bid }
+    Ok(Hand { cards, bid })
 }
 
 #[cfg(test)]
 mod tests {
     use std::{cmp::Ordering, str::FromStr};
 
-    use crate::{part1, part2, Card, Cards, HandType};
+    use advent_of_code_2023::{generate::generate, Day};
+    use itertools::Itertools;
+    use strum::IntoEnumIterator;
+
+    use crate::{
+        count_hand_types, hands_of_type, parse_hands, part1, part2, render_standings_csv,
+        render_standings_text, standings, Card, Cards, Dealing, Hand, HandType, ParseCardsError,
+        ParseHandError, ParseHandErrorKind, ParseOptions, RuleSet, TieBreak,
+    };
 
     #[test]
     fn test_example() {
@@ -288,8 +616,161 @@ mod tests {
         "#
         .trim();
 
-        assert_eq!(6440, part1(&input));
-        assert_eq!(5905, part2(&input));
+        assert_eq!(Ok(6440), part1(input, &ParseOptions::default()));
+        assert_eq!(Ok(5905), part2(input, &ParseOptions::default()));
+    }
+
+    #[test]
+    fn test_standings() {
+        let input = r#"
+            32T3K 765
+            T55J5 684
+            KK677 28
+            KTJJT 220
+            QQQJA 483
+        "#
+        .trim();
+        let tie_breaks = |rules: RuleSet| {
+            standings(
+                parse_hands(input, &ParseOptions::default()).unwrap(),
+                &rules,
+            )
+            .into_iter()
+            .map(|s| (s.cards.to_string(), s.tie_break))
+            .collect_vec()
+        };
+
+        assert_eq!(
+            vec![
+                ("32T3K".to_string(), TieBreak::Lowest),
+                ("KTJJT".to_string(), TieBreak::HandType),
+                ("KK677".to_string(), TieBreak::Card(2)),
+                ("T55J5".to_string(), TieBreak::HandType),
+                ("QQQJA".to_string(), TieBreak::Card(1)),
+            ],
+            tie_breaks(RuleSet::standard())
+        );
+        assert_eq!(
+            vec![
+                ("32T3K".to_string(), TieBreak::Lowest),
+                ("KK677".to_string(), TieBreak::HandType),
+                ("T55J5".to_string(), TieBreak::HandType),
+                ("QQQJA".to_string(), TieBreak::Card(1)),
+                ("KTJJT".to_string(), TieBreak::Card(1)),
+            ],
+            tie_breaks(RuleSet::jokers())
+        );
+
+        let hands = parse_hands(input, &ParseOptions::default()).unwrap();
+        let standings = standings(hands, &RuleSet::standard());
+        assert_eq!(
+            "rank,hand,type,beats_below_by,bid,winnings
+1,32T3K,pair,-,765,765
+2,KTJJT,two pair,hand type,220,440
+3,KK677,two pair,card 2,28,84
+4,T55J5,three of a kind,hand type,684,2736
+5,QQQJA,three of a kind,card 1,483,2415
+",
+            render_standings_csv(&standings)
+        );
+        assert_eq!(
+            "rank  hand   type             beats below by  bid  winnings
+1     32T3K  pair             -               765  765
+2     KTJJT  two pair         hand type       220  440
+3     KK677  two pair         card 2          28   84
+4     T55J5  three of a kind  hand type       684  2736
+5     QQQJA  three of a kind  card 1          483  2415
+",
+            render_standings_text(&standings)
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |input, options| parse_hands(input, &options).err().unwrap();
+
+        assert_eq!(
+            ParseHandError {
+                line: 2,
+                kind: ParseHandErrorKind::Card(ParseCardsError {
+                    character: 'k',
+                    position: 4
+                })
+            },
+            error("32T3K 765\nT55k5 684", ParseOptions::default())
+        );
+        assert_eq!(
+            ParseHandError {
+                line: 1,
+                kind: ParseHandErrorKind::Format
+            },
+            error("32T3K 765 junk", ParseOptions::default())
+        );
+        assert_eq!(
+            ParseHandError {
+                line: 1,
+                kind: ParseHandErrorKind::Length {
+                    expected: 5,
+                    actual: 4
+                }
+            },
+            error(
+                "32T3 765",
+                ParseOptions {
+                    hand_size: Some(5),
+                    ..Default::default()
+                }
+            )
+        );
+        assert_eq!(
+            ParseHandError {
+                line: 1,
+                kind: ParseHandErrorKind::BidOverflow("4294967296".to_string())
+            },
+            error("32T3K 4294967296", ParseOptions::default())
+        );
+        assert_eq!(
+            ParseHandError {
+                line: 2,
+                kind: ParseHandErrorKind::Length {
+                    expected: 5,
+                    actual: 4
+                }
+            },
+            error("32T3K 765\nT55J 684", ParseOptions::default())
+        );
+        assert_eq!(
+            ParseHandError {
+                line: 1,
+                kind: ParseHandErrorKind::InvalidBid("-5".to_string())
+            },
+            error("32T3K -5", ParseOptions::default())
+        );
+        assert_eq!(
+            "line 2: 'k' at position 4 is not a card",
+            error("32T3K 765\nT55k5 684", ParseOptions::default()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_large_bids() {
+        let input = "32T3K 4294967295\nT55J5 684";
+        let winnings = u32::MAX as u64 + 684 * 2;
+
+        assert_eq!(Ok(winnings), part1(input, &ParseOptions::default()));
+        assert_eq!(Ok(winnings), part2(input, &ParseOptions::default()));
+    }
+
+    #[test]
+    fn test_parse_ignore_case() {
+        let options = ParseOptions {
+            ignore_case: true,
+            hand_size: Some(5),
+        };
+        let hands = parse_hands("32t3k 765\nT55J5 684", &options).unwrap();
+
+        assert_eq!("32T3K", hands[0].cards.to_string());
+        assert_eq!(765, hands[0].bid);
     }
 
     #[test]
@@ -297,7 +778,12 @@ mod tests {
         let cs1 = Cards::from_str("77888").unwrap();
         let cs2 = Cards::from_str("77788").unwrap();
 
-        assert_eq!(Ordering::Greater, cs1.cmp(&cs2, |c1, c2| c1.cmp_part1(c2)));
+        let rules = RuleSet::standard();
+
+        assert_eq!(
+            Ordering::Greater,
+            cs1.cmp(&cs2, |c1, c2| rules.cmp_cards(c1, c2))
+        );
     }
 
     #[test]
@@ -307,18 +793,264 @@ mod tests {
         let cs3 = Cards::from_str("22JJ3").unwrap();
         let cs4 = Cards::from_str("JJJJJ").unwrap();
         let cs5 = Cards::from_str("JJ234").unwrap();
+        let rules = RuleSet::jokers();
 
-        assert_eq!(HandType::FullHouse, cs1.compute_hand_type_part2());
-        assert_eq!(HandType::FourOfAKind, cs2.compute_hand_type_part2());
-        assert_eq!(HandType::FourOfAKind, cs3.compute_hand_type_part2());
-        assert_eq!(HandType::FiveOfAKind, cs4.compute_hand_type_part2());
-        assert_eq!(HandType::ThreeOfAKind, cs5.compute_hand_type_part2());
+        assert_eq!(HandType(vec![3, 2]), rules.hand_type(&cs1));
+        assert_eq!(HandType(vec![4, 1]), rules.hand_type(&cs2));
+        assert_eq!(HandType(vec![4, 1]), rules.hand_type(&cs3));
+        assert_eq!(HandType(vec![5]), rules.hand_type(&cs4));
+        assert_eq!(HandType(vec![3, 1, 1]), rules.hand_type(&cs5));
     }
 
     #[test]
     fn test_joker_rank() {
-        assert_eq!(Ordering::Equal, Card::JACK.cmp_part2(&Card::JACK));
-        assert_eq!(Ordering::Greater, Card::TWO.cmp_part2(&Card::JACK));
-        assert_eq!(Ordering::Less, Card::JACK.cmp_part2(&Card::TWO));
+        let rules = RuleSet::jokers();
+
+        assert_eq!(Ordering::Equal, rules.cmp_cards(&Card::JACK, &Card::JACK));
+        assert_eq!(Ordering::Greater, rules.cmp_cards(&Card::TWO, &Card::JACK));
+        assert_eq!(Ordering::Less, rules.cmp_cards(&Card::JACK, &Card::TWO));
+    }
+
+    #[test]
+    fn test_hand_sizes() {
+        let input = r#"
+            223456 5
+            222345 7
+            222333 11
+            223344 13
+            22JJ33 17
+            JJJJJJ 19
+        "#
+        .trim();
+        let hand_type = |rules: RuleSet, raw| rules.hand_type(&Cards::from_str(raw).unwrap());
+
+        assert_eq!(
+            HandType(vec![3, 3]),
+            hand_type(RuleSet::standard(), "222333")
+        );
+        assert_eq!(HandType(vec![4, 2]), hand_type(RuleSet::jokers(), "22JJ33"));
+        assert_eq!(HandType(vec![6]), hand_type(RuleSet::jokers(), "JJJJJJ"));
+        assert_eq!(
+            Ok(5 + 13 * 2 + 17 * 3 + 7 * 4 + 11 * 5 + 19 * 6),
+            part1(input, &ParseOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_hand_type_names() {
+        let name = |signature: Vec<usize>| HandType(signature).to_string();
+
+        assert_eq!("high card", name(vec![1, 1, 1, 1, 1]));
+        assert_eq!("pair", name(vec![2, 1, 1, 1]));
+        assert_eq!("two pair", name(vec![2, 2, 1]));
+        assert_eq!("full house", name(vec![3, 2]));
+        assert_eq!("five of a kind", name(vec![5]));
+        assert_eq!("three pair", name(vec![2, 2, 2]));
+        assert_eq!("two triples", name(vec![3, 3]));
+        assert_eq!("six of a kind", name(vec![6]));
+        assert_eq!("four of a kind and pair", name(vec![4, 2]));
+        assert_eq!("two fours of a kind", name(vec![4, 4]));
+    }
+
+    #[test]
+    fn test_hand_type_counts() {
+        let counts = |rules: RuleSet, dealing| {
+            let (counts, total) = count_hand_types(&rules, 5, dealing);
+            (
+                counts
+                    .into_iter()
+                    .map(|(hand_type, count)| (hand_type.0, count))
+                    .collect_vec(),
+                total,
+            )
+        };
+
+        assert_eq!(
+            (
+                vec![
+                    (vec![1, 1, 1, 1, 1], 1317888),
+                    (vec![2, 1, 1, 1], 1098240),
+                    (vec![2, 2, 1], 123552),
+                    (vec![3, 1, 1], 54912),
+                    (vec![3, 2], 3744),
+                    (vec![4, 1], 624),
+                ],
+                2598960
+            ),
+            counts(RuleSet::standard(), Dealing::WithoutReplacement)
+        );
+        assert_eq!(
+            (
+                vec![
+                    (vec![1, 1, 1, 1, 1], 154440),
+                    (vec![2, 1, 1, 1], 171600),
+                    (vec![2, 2, 1], 25740),
+                    (vec![3, 1, 1], 17160),
+                    (vec![3, 2], 1560),
+                    (vec![4, 1], 780),
+                    (vec![5], 13),
+                ],
+                371293
+            ),
+            counts(RuleSet::standard(), Dealing::WithReplacement)
+        );
+
+        let (jokers, total) = count_hand_types(&RuleSet::jokers(), 5, Dealing::WithReplacement);
+        assert_eq!(371293, total);
+        assert_eq!(
+            Some(&(13 + 12 * 5 * 2 + 12 * 10 * 2)),
+            jokers.get(&HandType(vec![5]))
+        );
+    }
+
+    #[test]
+    fn test_hands_of_type() {
+        let rules = RuleSet::standard();
+        let (counts, _) = count_hand_types(&rules, 3, Dealing::WithReplacement);
+
+        for hand_type in [HandType(vec![3]), HandType(vec![2, 1])] {
+            let hands = hands_of_type(&rules, 3, &hand_type).collect_vec();
+            assert_eq!(counts[&hand_type], hands.len() as u128);
+            assert!(hands
+                .iter()
+                .all(|cards| rules.hand_type(cards) == hand_type));
+        }
+    }
+
+    #[test]
+    fn test_several_wild_cards() {
+        let rules = RuleSet {
+            order: vec![
+                Card::JACK,
+                Card::QUEEN,
+                Card::TWO,
+                Card::THREE,
+                Card::FOUR,
+                Card::FIVE,
+                Card::SIX,
+                Card::SEVEN,
+                Card::EIGHT,
+                Card::NINE,
+                Card::TEN,
+                Card::KING,
+                Card::ACE,
+            ],
+            wild: vec![Card::JACK, Card::QUEEN],
+        };
+        let hand_type = |raw| rules.hand_type(&Cards::from_str(raw).unwrap());
+
+        assert_eq!(HandType(vec![4, 1]), hand_type("JQ223"));
+        assert_eq!(HandType(vec![3, 1, 1]), hand_type("JQ234"));
+        assert_eq!(HandType(vec![5]), hand_type("QQJJJ"));
+        assert_eq!(HandType(vec![3, 2]), hand_type("Q2233"));
+        assert_eq!(Ordering::Less, rules.cmp_cards(&Card::QUEEN, &Card::TWO));
+    }
+
+    /// Classifies a 5-card hand without wild cards from its number of distinct cards and
+    /// largest group, independently of `Cards::get_signature`.
+    fn classify(ranks: &[usize]) -> HandType {
+        let mut counts = [0; 13];
+        for rank in ranks {
+            counts[*rank] += 1;
+        }
+        let distinct = counts.iter().filter(|count| **count > 0).count();
+        let largest = *counts.iter().max().unwrap();
+        match (distinct, largest) {
+            (1, _) => HandType(vec![5]),
+            (2, 4) => HandType(vec![4, 1]),
+            (2, _) => HandType(vec![3, 2]),
+            (3, 3) => HandType(vec![3, 1, 1]),
+            (3, _) => HandType(vec![2, 2, 1]),
+            (4, _) => HandType(vec![2, 1, 1, 1]),
+            _ => HandType(vec![1, 1, 1, 1, 1]),
+        }
+    }
+
+    /// The best hand type over every way of replacing each wild card by any other card.
+    fn best_substitution(ranks: &mut [usize], i: usize, wild: &[usize]) -> HandType {
+        if i == ranks.len() {
+            return classify(ranks);
+        }
+        if !wild.contains(&ranks[i]) {
+            return best_substitution(ranks, i + 1, wild);
+        }
+        let original = ranks[i];
+        let best = (0..13)
+            .filter(|rank| !wild.contains(rank))
+            .map(|rank| {
+                ranks[i] = rank;
+                best_substitution(ranks, i + 1, wild)
+            })
+            .max()
+            .unwrap();
+        ranks[i] = original;
+        best
+    }
+
+    #[test]
+    fn test_hand_types_against_substitution() {
+        let cards = Card::iter().collect_vec();
+        let two_wild = RuleSet {
+            order: Card::iter().collect(),
+            wild: vec![Card::JACK, Card::QUEEN],
+        };
+
+        for rules in [RuleSet::standard(), RuleSet::jokers(), two_wild] {
+            let wild = rules
+                .wild
+                .iter()
+                .map(|card| cards.iter().position(|c| c == card).unwrap())
+                .collect_vec();
+            // Hand types don't depend on card order, so every multiset of cards covers
+            // all the hands that are its permutations.
+            for hand in (0..13).combinations_with_replacement(5) {
+                let expected = best_substitution(&mut hand.clone(), 0, &wild);
+                let actual =
+                    rules.hand_type(&hand.iter().map(|rank| cards[*rank].clone()).collect());
+                assert_eq!(expected, actual, "{:?} with wild {:?}", hand, rules.wild);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated() {
+        for seed in 0..20 {
+            let input = generate(Day::DAY7, seed, 50);
+            let hands = parse_hands(&input, &ParseOptions::default()).unwrap();
+
+            assert_eq!(50, hands.len());
+            assert!(part1(&input, &ParseOptions::default()).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_orderings_are_total() {
+        let input = generate(Day::DAY7, 11, 20) + "AAAAA 1\nAAAAA 2\nJJJJJ 3\n";
+        let hands = parse_hands(&input, &ParseOptions::default()).unwrap();
+        for rules in [RuleSet::standard(), RuleSet::jokers()] {
+            let by_cards =
+                |a: &Hand, b: &Hand| a.cards.cmp(&b.cards, |c1, c2| rules.cmp_cards(c1, c2));
+            let by_hand = |a: &Hand, b: &Hand| rules.cmp_hands(a, b);
+            for cmp in [&by_cards as &dyn Fn(&Hand, &Hand) -> Ordering, &by_hand] {
+                for (a, b) in hands.iter().cartesian_product(&hands) {
+                    assert_eq!(cmp(a, b), cmp(b, a).reverse());
+                    assert_eq!(a.cards.0 == b.cards.0, cmp(a, b) == Ordering::Equal);
+                }
+                for (a, b, c) in hands.iter().tuple_combinations() {
+                    for (a, b, c) in [
+                        (a, b, c),
+                        (a, c, b),
+                        (b, a, c),
+                        (b, c, a),
+                        (c, a, b),
+                        (c, b, a),
+                    ] {
+                        if cmp(a, b).is_le() && cmp(b, c).is_le() {
+                            assert!(cmp(a, c).is_le());
+                        }
+                    }
+                }
+            }
+        }
     }
 }
@@ -1,99 +1,994 @@
-use std::{collections::HashMap, iter::repeat_with, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    hint::black_box,
+    time::Instant,
+};
 
-use advent_of_code_2023::{read_input, Day};
+use advent_of_code_2023::{generate::Rng, read_input, Day};
 use itertools::Itertools;
-use strum::EnumString;
 
-#[derive(EnumString)]
-enum Instruction {
-    #[strum(serialize = "L")]
-    LEFT,
-    #[strum(serialize = "R")]
-    RIGHT,
-}
+/// The index of the exit to take. In networks with unlabelled exits `L` and
+/// `R` stand for the first and second, and digits for any other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Instruction(usize);
 
 const START_KEY: &str = "AAA";
 const END_KEY: &str = "ZZZ";
 
+/// Node keys are three base-36 digits, so every key packs into an index below
+/// `KEY_SPACE`.
+const KEY_SPACE: usize = 36 * 36 * 36;
+const NO_NODE: u32 = u32::MAX;
+
 fn main() -> std::io::Result<()> {
     let input = read_input(Day::DAY8)?;
 
-    println!("Part 1: {:?}", part1(&input));
-    println!("Part 2: {}", part2(&input));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let invalid_input = |error| std::io::Error::new(std::io::ErrorKind::InvalidInput, error);
+
+    if let Some(steps) = args.iter().find_map(|arg| arg.strip_prefix("--bench=")) {
+        bench(steps.parse().map_err(invalid_input)?);
+        return Ok(());
+    }
+
+    let invalid_data = |error| std::io::Error::new(std::io::ErrorKind::InvalidData, error);
+
+    if let Some(budget) = args.iter().find_map(|arg| arg.strip_prefix("--simulate=")) {
+        let budget: u64 = budget.parse().map_err(invalid_input)?;
+        let interval = args
+            .iter()
+            .find_map(|arg| arg.strip_prefix("--interval="))
+            .map(|interval| interval.parse().map_err(invalid_input))
+            .transpose()?
+            .unwrap_or((budget / 10).max(1));
+        let network = Network::parse(&input).map_err(invalid_data)?;
+        let instructions = parse_instructions(&input, &network).map_err(invalid_data)?;
+        let simulated = simulate(&network, &instructions, budget, interval, |step, ids| {
+            let keys = ids.iter().map(|&id| &network.names[id as usize]).join(" ");
+            println!("Step {}: {}", step, keys);
+        })
+        .map_err(invalid_data)?;
+        match simulated {
+            Some(steps) => {
+                println!("Simulated: {}", steps);
+                let fast = part2(&input).map_err(invalid_data)?;
+                if fast != steps {
+                    let message = format!("part 2 gives {} but the simulation {}", fast, steps);
+                    return Err(std::io::Error::other(message));
+                }
+                println!("Part 2 agrees");
+            }
+            None => println!("No arrival within {} steps", budget),
+        }
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--analyze") {
+        let network = Network::parse(&input).map_err(invalid_data)?;
+        let instructions = parse_instructions(&input, &network).map_err(invalid_data)?;
+        print!(
+            "{}",
+            render_analysis(&network, &analyze(&network, &instructions))
+        );
+        return Ok(());
+    }
+
+    let dot = args.iter().any(|arg| arg == "--dot");
+    let json = args.iter().any(|arg| arg == "--json");
+    if dot || json {
+        let network = Network::parse(&input).map_err(invalid_data)?;
+        let path = match args.iter().find_map(|arg| arg.strip_prefix("--path-from=")) {
+            Some(start_key) => {
+                let instructions = parse_instructions(&input, &network).map_err(invalid_data)?;
+                let path = network
+                    .path(start_key, &instructions, end_predicate(start_key))
+                    .map_err(invalid_data)?;
+                Some(path)
+            }
+            None => None,
+        };
+        if dot {
+            print!("{}", render_dot(&network, path.as_deref()));
+        } else {
+            print!("{}", render_json(&network, path.as_deref()));
+        }
+        return Ok(());
+    }
+
+    println!("Part 1: {}", part1(&input).map_err(invalid_data)?);
+    println!("Part 2: {}", part2(&input).map_err(invalid_data)?);
 
     Ok(())
 }
 
-fn part1(input: &str) -> Option<u64> {
-    let instructions = parse_instructions(input);
-    let map = parse_map(input);
-    get_path_length(START_KEY, &instructions, &map, |key| key == END_KEY)
+#[derive(Debug, PartialEq, Eq)]
+enum PathError {
+    /// A node is referenced but never defined.
+    UnknownNode(String),
+    /// The walk from this start loops without ever reaching an end node.
+    Unreachable(String),
+    /// Every ghost reaches an end node, but never all on the same step.
+    NeverTogether,
+    /// A node key is not three uppercase letters or digits.
+    InvalidKey(String),
+    /// A map line is not of the form `AAA = (BBB, CCC)` or
+    /// `AAA = (left: BBB, right: CCC)`.
+    InvalidEntry(String),
+    /// An instruction names no exit of the network.
+    UnknownInstruction(String),
+    /// A walk takes an exit the node it is on does not have.
+    MissingExit { key: String, exit: String },
+}
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::UnknownNode(key) => write!(f, "node {} is not in the map", key),
+            PathError::Unreachable(key) => write!(f, "no end node is reachable from {}", key),
+            PathError::NeverTogether => f.write_str("the ghosts never arrive together"),
+            PathError::InvalidKey(key) => write!(f, "'{}' is not a node key", key),
+            PathError::InvalidEntry(line) => write!(f, "'{}' is not a map entry", line),
+            PathError::UnknownInstruction(token) => {
+                write!(f, "'{}' is not an instruction", token)
+            }
+            PathError::MissingExit { key, exit } => {
+                write!(f, "node {} has no exit {}", key, exit)
+            }
+        }
+    }
+}
+impl std::error::Error for PathError {}
+
+/// What a walk from `start_key` is looking for: `ZZZ` from `AAA`, as in part
+/// 1, and any `..Z` node otherwise, as in part 2.
+fn end_predicate(start_key: &str) -> fn(&str) -> bool {
+    if start_key == START_KEY {
+        |key| key == END_KEY
+    } else {
+        |key| key.ends_with("Z")
+    }
+}
+
+fn part1(input: &str) -> Result<u64, PathError> {
+    let network = Network::parse(input)?;
+    let instructions = parse_instructions(input, &network)?;
+    let passes = network.passes(&instructions, |key| key == END_KEY);
+    get_path_length(&network, START_KEY, &instructions, &passes)
+}
+
+/// The map with every node interned to a dense id.
+struct Network {
+    names: Vec<String>,
+    /// Node ids by packed key, or `NO_NODE` for keys not in the map.
+    ids: Vec<u32>,
+    /// Exits by node id and exit index, with `NO_NODE` for exits a node does
+    /// not have. Nodes that are referenced but never defined have none.
+    exits: Vec<Vec<u32>>,
+    /// The name of each exit index, if the map labels its exits.
+    labels: Vec<String>,
+    /// The most exits any node has.
+    arity: usize,
+}
+
+/// Where one full pass of the instructions takes a node.
+struct Pass {
+    /// The node the pass ends on, or `NO_NODE` if it runs into an undefined node.
+    end: u32,
+    /// The first step of the pass, counting from 1, that lands on a target.
+    hit: Option<u32>,
+}
+
+impl Network {
+    fn parse(input: &str) -> Result<Network, PathError> {
+        let mut network = Network {
+            names: Vec::new(),
+            ids: vec![NO_NODE; KEY_SPACE],
+            exits: Vec::new(),
+            labels: Vec::new(),
+            arity: 0,
+        };
+        let mut labelled = None;
+        for line in input.lines().skip(2).filter(|line| !line.trim().is_empty()) {
+            let (key, entry) = parse_map_entry(line)?;
+            let invalid_entry = || PathError::InvalidEntry(line.trim().to_string());
+            // Exits are either all labelled or all positional, across the map.
+            let labelled = *labelled.get_or_insert(entry[0].0.is_some());
+            let id = network.intern(key)?;
+            let mut exits = Vec::new();
+            for (position, (label, next)) in entry.into_iter().enumerate() {
+                let exit = match label {
+                    Some(label) => network.label(label),
+                    None => position,
+                };
+                if exits.len() <= exit {
+                    exits.resize(exit + 1, NO_NODE);
+                }
+                if exits[exit] != NO_NODE || label.is_some() != labelled {
+                    return Err(invalid_entry());
+                }
+                exits[exit] = network.intern(next)?;
+            }
+            network.arity = network.arity.max(exits.len());
+            network.exits[id as usize] = exits;
+        }
+        Ok(network)
+    }
+
+    fn label(&mut self, label: &str) -> usize {
+        self.labels
+            .iter()
+            .position(|known| known == label)
+            .unwrap_or_else(|| {
+                self.labels.push(label.to_string());
+                self.labels.len() - 1
+            })
+    }
+
+    fn instruction(&self, token: &str) -> Result<Instruction, PathError> {
+        let exit = if self.labels.is_empty() {
+            match token {
+                "L" => Some(0),
+                "R" => Some(1),
+                _ => token.parse().ok(),
+            }
+        } else {
+            self.labels.iter().position(|label| label == token)
+        };
+        exit.map(Instruction)
+            .ok_or_else(|| PathError::UnknownInstruction(token.to_string()))
+    }
+
+    fn intern(&mut self, key: &str) -> Result<u32, PathError> {
+        let index = pack_key(key).ok_or_else(|| PathError::InvalidKey(key.to_string()))?;
+        if self.ids[index] == NO_NODE {
+            self.ids[index] = self.names.len() as u32;
+            self.names.push(key.to_string());
+            self.exits.push(Vec::new());
+        }
+        Ok(self.ids[index])
+    }
+
+    fn id(&self, key: &str) -> Result<u32, PathError> {
+        pack_key(key)
+            .map(|index| self.ids[index])
+            .filter(|&id| id != NO_NODE && self.is_defined(id))
+            .ok_or_else(|| PathError::UnknownNode(key.to_string()))
+    }
+
+    fn is_defined(&self, id: u32) -> bool {
+        !self.exits[id as usize].is_empty()
+    }
+
+    /// The defined nodes and their ids.
+    fn nodes(&self) -> impl Iterator<Item = (u32, &str)> {
+        (0..)
+            .zip(&self.names)
+            .filter(|&(id, _)| self.is_defined(id))
+            .map(|(id, name)| (id, name.as_str()))
+    }
+
+    fn step(&self, id: u32, instruction: Instruction) -> Result<u32, PathError> {
+        if !self.is_defined(id) {
+            return Err(PathError::UnknownNode(self.names[id as usize].clone()));
+        }
+        let Instruction(exit) = instruction;
+        self.exits[id as usize]
+            .get(exit)
+            .copied()
+            .filter(|&next| next != NO_NODE)
+            .ok_or_else(|| PathError::MissingExit {
+                key: self.names[id as usize].clone(),
+                exit: self.exit_name(exit),
+            })
+    }
+
+    /// The label of an exit, `L` or `R` for the two unlabelled exits of an
+    /// L/R map, and its index for wider unlabelled maps.
+    fn exit_name(&self, exit: usize) -> String {
+        match (self.labels.get(exit), ["L", "R"].get(exit)) {
+            (Some(label), _) => label.clone(),
+            (None, Some(name)) if self.arity <= 2 => name.to_string(),
+            (None, _) => exit.to_string(),
+        }
+    }
+
+    /// The exits taken by the walk [`get_path_length`] measures.
+    fn path(
+        &self,
+        start_key: &str,
+        instructions: &[Instruction],
+        predicate: impl Fn(&str) -> bool,
+    ) -> Result<Vec<(u32, Instruction)>, PathError> {
+        let passes = self.passes(instructions, predicate);
+        let length = get_path_length(self, start_key, instructions, &passes)?;
+        let mut id = self.id(start_key)?;
+        instructions
+            .iter()
+            .cycle()
+            .take(length as usize)
+            .map(|&instruction| {
+                let from = id;
+                id = self.step(id, instruction)?;
+                Ok((from, instruction))
+            })
+            .collect()
+    }
+
+    /// The strongly connected components of the network, ignoring the
+    /// instructions, each sorted by id and in order of their first node.
+    fn components(&self) -> Vec<Vec<u32>> {
+        let mut tarjan = Tarjan {
+            network: self,
+            index: vec![None; self.names.len()],
+            low: vec![0; self.names.len()],
+            on_stack: vec![false; self.names.len()],
+            stack: Vec::new(),
+            next_index: 0,
+            components: Vec::new(),
+        };
+        for id in 0..self.names.len() as u32 {
+            if tarjan.index[id as usize].is_none() {
+                tarjan.visit(id);
+            }
+        }
+        let mut components = tarjan.components;
+        for component in &mut components {
+            component.sort();
+        }
+        components.sort();
+        components
+    }
+
+    /// Runs every node through one full pass of `instructions`, noting the
+    /// first step on a node matching `predicate`.
+    fn passes(&self, instructions: &[Instruction], predicate: impl Fn(&str) -> bool) -> Vec<Pass> {
+        let targets: Vec<bool> = self.names.iter().map(|name| predicate(name)).collect();
+        (0..self.names.len() as u32)
+            .map(|mut id| {
+                let mut hit = None;
+                for (i, &instruction) in (1..).zip(instructions) {
+                    match self.step(id, instruction) {
+                        Ok(next) => id = next,
+                        Err(_) => return Pass { end: NO_NODE, hit },
+                    }
+                    if hit.is_none() && targets[id as usize] {
+                        hit = Some(i);
+                    }
+                }
+                Pass { end: id, hit }
+            })
+            .collect()
+    }
+}
+
+/// State for Tarjan's strongly connected components algorithm.
+struct Tarjan<'a> {
+    network: &'a Network,
+    index: Vec<Option<u32>>,
+    low: Vec<u32>,
+    on_stack: Vec<bool>,
+    stack: Vec<u32>,
+    next_index: u32,
+    components: Vec<Vec<u32>>,
 }
 
+impl Tarjan<'_> {
+    fn visit(&mut self, id: u32) {
+        let i = id as usize;
+        self.index[i] = Some(self.next_index);
+        self.low[i] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(id);
+        self.on_stack[i] = true;
+
+        for &next in &self.network.exits[i] {
+            if next == NO_NODE {
+                continue;
+            }
+            match self.index[next as usize] {
+                None => {
+                    self.visit(next);
+                    self.low[i] = self.low[i].min(self.low[next as usize]);
+                }
+                Some(index) if self.on_stack[next as usize] => {
+                    self.low[i] = self.low[i].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.low[i]) == self.index[i] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member as usize] = false;
+                component.push(member);
+                if member == id {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+fn pack_key(key: &str) -> Option<usize> {
+    if key.len() != 3 {
+        return None;
+    }
+    key.chars().try_fold(0, |index, char| {
+        // `to_digit` also takes lowercase letters, which would pack `aaa` onto `AAA`.
+        if !char.is_ascii_digit() && !char.is_ascii_uppercase() {
+            return None;
+        }
+        Some(index * 36 + char.to_digit(36)? as usize)
+    })
+}
+
+/// Follows `instructions` from `start_key` to the first target of `passes`,
+/// a whole pass at a time, giving up once a pass starts on a node twice.
 fn get_path_length(
+    network: &Network,
     start_key: &str,
-    instructions: &Vec<Instruction>,
-    map: &HashMap<&str, (&str, &str)>,
-    predicate: impl Fn(&str) -> bool,
-) -> Option<u64> {
-    let instructions = repeat_with(|| instructions).into_iter().flatten();
-    let mut key = start_key;
-    for (i, ins) in (1u64..).zip(instructions) {
-        let next = match ins {
-            Instruction::LEFT => map.get(key).unwrap().0,
-            Instruction::RIGHT => map.get(key).unwrap().1,
+    instructions: &[Instruction],
+    passes: &[Pass],
+) -> Result<u64, PathError> {
+    let mut id = network.id(start_key)?;
+    let mut seen = vec![false; passes.len()];
+    let mut length = 0;
+    // An empty instruction list never moves, so never arrives either.
+    while !instructions.is_empty() && !seen[id as usize] {
+        seen[id as usize] = true;
+        let pass = &passes[id as usize];
+        if let Some(hit) = pass.hit {
+            return Ok(length + hit as u64);
+        }
+        if pass.end == NO_NODE {
+            // Replay the pass to find the node it got stuck on.
+            let stuck = instructions
+                .iter()
+                .try_fold(id, |id, &instruction| network.step(id, instruction));
+            return Err(stuck.unwrap_err());
+        }
+        id = pass.end;
+        length += instructions.len() as u64;
+    }
+    Err(PathError::Unreachable(start_key.to_string()))
+}
+
+fn part2(input: &str) -> Result<u64, PathError> {
+    let network = Network::parse(input)?;
+    let instructions = parse_instructions(input, &network)?;
+
+    let ghosts: Vec<Ghost> = ghost_starts(&network)
+        .into_iter()
+        .map(|id| Ghost::walk(&network, id, &instructions, |key| key.ends_with("Z")))
+        .collect::<Result<_, _>>()?;
+
+    if ghosts.iter().all(Ghost::hits_once_per_cycle) {
+        return Ok(ghosts.iter().map(|ghost| ghost.cycle).fold(1, lcm));
+    }
+    first_common_hit(&ghosts).ok_or_else(|| {
+        // The ghosts may only have failed to meet because one got stuck first.
+        ghosts
+            .into_iter()
+            .filter(|ghost| ghost.stuck.is_some())
+            .min_by_key(|ghost| ghost.tail)
+            .and_then(|ghost| ghost.stuck)
+            .unwrap_or(PathError::NeverTogether)
+    })
+}
+
+/// The `..A` nodes, in key order.
+fn ghost_starts(network: &Network) -> Vec<u32> {
+    network
+        .nodes()
+        .filter(|(_, key)| key.ends_with("A"))
+        .sorted_by_key(|&(_, key)| key)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Steps every ghost at once until they all stand on `..Z` nodes, for at
+/// most `budget` steps, passing their positions to `report` every `interval`
+/// steps. Gives `None` if the budget runs out first.
+fn simulate(
+    network: &Network,
+    instructions: &[Instruction],
+    budget: u64,
+    interval: u64,
+    mut report: impl FnMut(u64, &[u32]),
+) -> Result<Option<u64>, PathError> {
+    let mut ids = ghost_starts(network);
+    let arrived = |ids: &[u32]| {
+        ids.iter()
+            .all(|&id| network.names[id as usize].ends_with('Z'))
+    };
+    for (step, &instruction) in (1..=budget).zip(instructions.iter().cycle()) {
+        for id in ids.iter_mut() {
+            *id = network.step(*id, instruction)?;
+        }
+        if step.is_multiple_of(interval) {
+            report(step, &ids);
+        }
+        if arrived(&ids) {
+            return Ok(Some(step));
+        }
+    }
+    Ok(None)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Ghost {
+    tail: u64,
+    cycle: u64,
+    /// Hits before the first step that is followed periodically.
+    tail_hits: Vec<u64>,
+    /// Hits in the first period, each recurring every `cycle` steps.
+    cycle_hits: Vec<u64>,
+    /// Why the walk stopped after `tail` steps, if it ran into a node it can't
+    /// leave. A stuck ghost has no cycle and only arrives on its tail hits.
+    stuck: Option<PathError>,
+}
+
+impl Ghost {
+    fn walk(
+        network: &Network,
+        start: u32,
+        instructions: &[Instruction],
+        predicate: impl Fn(&str) -> bool,
+    ) -> Result<Ghost, PathError> {
+        // An empty instruction list never moves, so never arrives either.
+        if instructions.is_empty() {
+            return Err(PathError::Unreachable(
+                network.names[start as usize].clone(),
+            ));
+        }
+        let mut seen = vec![u64::MAX; network.names.len() * instructions.len()];
+        let mut ids = vec![start];
+        let mut id = start;
+        let (tail, end, stuck) = loop {
+            let step = ids.len() as u64 - 1;
+            let position = step as usize % instructions.len();
+            let state = id as usize * instructions.len() + position;
+            if seen[state] != u64::MAX {
+                break (seen[state], step, None);
+            }
+            seen[state] = step;
+            match network.step(id, instructions[position]) {
+                Ok(next) => id = next,
+                Err(error) => break (step + 1, step + 1, Some(error)),
+            }
+            ids.push(id);
+        };
+        let cycle = end - tail;
+        // Step 0 is the start itself, which never counts as an arrival.
+        let first = tail.max(1);
+        let hits = |steps: std::ops::Range<u64>| {
+            steps
+                .filter(|&step| predicate(&network.names[ids[step as usize] as usize]))
+                .collect()
         };
-        if predicate(next) {
-            return Some(i);
+
+        let ghost = Ghost {
+            tail: first,
+            cycle,
+            tail_hits: hits(1..first),
+            cycle_hits: hits(first..first + cycle),
+            stuck,
+        };
+        if ghost.tail_hits.is_empty() && ghost.cycle_hits.is_empty() {
+            return Err(ghost
+                .stuck
+                .unwrap_or_else(|| PathError::Unreachable(network.names[start as usize].clone())));
+        }
+        Ok(ghost)
+    }
+
+    fn hits(&self, step: u64) -> bool {
+        if step < self.tail {
+            self.tail_hits.contains(&step)
         } else {
-            key = next
+            self.cycle_hits
+                .iter()
+                .any(|&hit| step.abs_diff(hit).is_multiple_of(self.cycle))
         }
     }
-    None
+
+    /// Whether the ghost only ever arrives at multiples of its cycle length,
+    /// which is what makes the LCM of the cycle lengths the answer.
+    fn hits_once_per_cycle(&self) -> bool {
+        self.tail_hits.is_empty() && self.cycle_hits == [self.cycle]
+    }
 }
 
-fn part2(input: &str) -> u64 {
-    let instructions = parse_instructions(input);
-    let map = parse_map(input);
+/// Finds the first step at which every ghost arrives at once.
+fn first_common_hit(ghosts: &[Ghost]) -> Option<u64> {
+    let latest = ghosts.iter().max_by_key(|ghost| ghost.tail)?;
+    // Before the longest tail has run out, only its own hits are candidates.
+    if let Some(&step) = latest
+        .tail_hits
+        .iter()
+        .find(|&&step| ghosts.iter().all(|ghost| ghost.hits(step)))
+    {
+        return Some(step);
+    }
+
+    ghosts
+        .iter()
+        .map(|ghost| {
+            ghost
+                .cycle_hits
+                .iter()
+                .map(|&hit| (hit as i128 % ghost.cycle as i128, ghost.cycle as i128))
+                .collect::<Vec<_>>()
+        })
+        .multi_cartesian_product()
+        .filter_map(|residues| residues.into_iter().try_fold((0, 1), crt))
+        .map(|(residue, modulus)| {
+            let residue = residue as u64;
+            let modulus = modulus as u64;
+            if residue >= latest.tail {
+                residue
+            } else {
+                residue + (latest.tail - residue).div_ceil(modulus) * modulus
+            }
+        })
+        .min()
+}
 
-    map.keys()
-        .cloned()
-        .filter(|key| key.ends_with("A"))
-        .map(|key| get_path_length(key, &instructions, &map, |key| key.ends_with("Z")).unwrap())
-        .fold(1, |a, b| lcm(a, b))
+/// Combines `x = a (mod m)` and `x = b (mod n)` into a single congruence,
+/// if the two are compatible.
+fn crt((a, m): (i128, i128), (b, n): (i128, i128)) -> Option<(i128, i128)> {
+    let (g, p, _) = extended_gcd(m, n);
+    if (b - a) % g != 0 {
+        return None;
+    }
+    let lcm = m / g * n;
+    let x = a + (b - a) / g * p % (n / g) * m;
+    Some((x.rem_euclid(lcm), lcm))
+}
+
+/// Returns `(g, p, q)` with `g = gcd(a, b) = p * a + q * b`.
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, p, q) = extended_gcd(b, a % b);
+        (g, q, p - a / b * q)
+    }
 }
 
 fn lcm(a: u64, b: u64) -> u64 {
     a * b / gcd::euclid_u64(a, b)
 }
 
-fn parse_instructions(input: &str) -> Vec<Instruction> {
-    input
-        .lines()
-        .next()
-        .unwrap()
-        .chars()
-        .map(|char| Instruction::from_str(&char.to_string()).unwrap())
+/// The shape of a network, independent of any one walk.
+#[derive(Debug, PartialEq, Eq)]
+struct Analysis {
+    /// Strongly connected components with more than one node, or a node that
+    /// can step to itself.
+    components: Vec<Vec<u32>>,
+    /// Nodes no exit path leads to from a start node.
+    unreachable: Vec<u32>,
+    /// Nodes all of whose exits lead back to themselves.
+    dead_ends: Vec<u32>,
+    /// The end nodes the instructions ever lead each start node to.
+    reachable_ends: Vec<(u32, Vec<u32>)>,
+}
+
+fn analyze(network: &Network, instructions: &[Instruction]) -> Analysis {
+    let ends_with = |suffix: char| {
+        network
+            .nodes()
+            .filter(move |(_, key)| key.ends_with(suffix))
+            .map(|(id, _)| id)
+    };
+    let is_end = |id: u32| network.names[id as usize].ends_with('Z');
+
+    let components = network
+        .components()
+        .into_iter()
+        .filter(|component| match component[..] {
+            [id] => network.exits[id as usize].contains(&id),
+            _ => true,
+        })
+        .collect();
+
+    let mut reached = vec![false; network.names.len()];
+    let mut stack: Vec<u32> = ends_with('A').collect();
+    while let Some(id) = stack.pop() {
+        if !std::mem::replace(&mut reached[id as usize], true) {
+            stack.extend(
+                network.exits[id as usize]
+                    .iter()
+                    .filter(|&&next| next != NO_NODE),
+            );
+        }
+    }
+    let unreachable = (0..)
+        .zip(reached)
+        .filter(|&(_, reached)| !reached)
+        .map(|(id, _)| id)
+        .collect();
+
+    let dead_ends = network
+        .nodes()
+        .map(|(id, _)| id)
+        .filter(|&id| network.exits[id as usize].iter().all(|&next| next == id))
+        .collect();
+
+    // Each walk is deterministic, so it covers everything it will ever reach
+    // by the time a (node, instruction index) state repeats, or it gets stuck.
+    let reachable_ends = ends_with('A')
+        .map(|start| {
+            let mut seen = HashSet::new();
+            let mut ends = Vec::new();
+            let mut id = start;
+            for position in (0..instructions.len()).cycle() {
+                if !seen.insert((id, position)) {
+                    break;
+                }
+                match network.step(id, instructions[position]) {
+                    Ok(next) => id = next,
+                    Err(_) => break,
+                }
+                if is_end(id) && !ends.contains(&id) {
+                    ends.push(id);
+                }
+            }
+            ends.sort();
+            (start, ends)
+        })
+        .collect();
+
+    Analysis {
+        components,
+        unreachable,
+        dead_ends,
+        reachable_ends,
+    }
+}
+
+/// Renders an [`Analysis`] with node keys in place of ids.
+fn render_analysis(network: &Network, analysis: &Analysis) -> String {
+    let keys = |ids: &[u32]| match ids {
+        [] => "none".to_string(),
+        _ => ids
+            .iter()
+            .map(|&id| network.names[id as usize].as_str())
+            .join(" "),
+    };
+    let mut report = format!("{} cyclic components\n", analysis.components.len());
+    for component in &analysis.components {
+        report += &format!("  {} nodes: {}\n", component.len(), keys(component));
+    }
+    report += &format!(
+        "unreachable from any start: {}\n",
+        keys(&analysis.unreachable)
+    );
+    report += &format!("dead ends: {}\n", keys(&analysis.dead_ends));
+    for (start, ends) in &analysis.reachable_ends {
+        report += &format!(
+            "{} reaches: {}\n",
+            network.names[*start as usize],
+            keys(ends)
+        );
+    }
+    report
+}
+
+/// Renders the network as a Graphviz digraph, with start nodes in green, end
+/// nodes in red and the exits taken by `path` drawn bold.
+fn render_dot(network: &Network, path: Option<&[(u32, Instruction)]>) -> String {
+    let taken: HashSet<(u32, usize)> = path
+        .unwrap_or_default()
+        .iter()
+        .map(|&(id, Instruction(exit))| (id, exit))
+        .collect();
+    let mut dot = String::from("digraph network {\n");
+    for (id, key) in (0..).zip(&network.names) {
+        let style = if !network.is_defined(id) {
+            " [style=dashed]"
+        } else if key.ends_with('A') {
+            " [style=filled, fillcolor=palegreen]"
+        } else if key.ends_with('Z') {
+            " [style=filled, fillcolor=lightcoral]"
+        } else {
+            ""
+        };
+        dot += &format!("    \"{}\"{};\n", key, style);
+    }
+    for (id, exits) in (0..).zip(&network.exits) {
+        for (exit, &next) in exits.iter().enumerate() {
+            if next == NO_NODE {
+                continue;
+            }
+            let highlight = if taken.contains(&(id, exit)) {
+                ", color=red, penwidth=2"
+            } else {
+                ""
+            };
+            dot += &format!(
+                "    \"{}\" -> \"{}\" [label={:?}{}];\n",
+                network.names[id as usize],
+                network.names[next as usize],
+                network.exit_name(exit),
+                highlight
+            );
+        }
+    }
+    dot + "}\n"
+}
+
+/// Renders the network as JSON, mapping each defined node to its exits by
+/// name, alongside the start and end nodes and the nodes `path` visits.
+fn render_json(network: &Network, path: Option<&[(u32, Instruction)]>) -> String {
+    let quote = |id: u32| format!("{:?}", network.names[id as usize]);
+    let nodes = network
+        .nodes()
+        .map(|(id, key)| {
+            let exits = network.exits[id as usize]
+                .iter()
+                .enumerate()
+                .filter(|&(_, &next)| next != NO_NODE)
+                .map(|(exit, &next)| format!("{:?}: {}", network.exit_name(exit), quote(next)))
+                .join(", ");
+            format!("    {:?}: {{{}}}", key, exits)
+        })
+        .join(",\n");
+    let ending = |suffix: char| {
+        network
+            .nodes()
+            .filter(|(_, key)| key.ends_with(suffix))
+            .map(|(id, _)| quote(id))
+            .join(", ")
+    };
+
+    let mut json = format!(
+        "{{\n  \"nodes\": {{\n{}\n  }},\n  \"starts\": [{}],\n  \"ends\": [{}]",
+        nodes,
+        ending('A'),
+        ending('Z')
+    );
+    if let Some(path) = path {
+        let last = path
+            .last()
+            .map(|&(id, instruction)| network.step(id, instruction).unwrap());
+        let visited = path
+            .iter()
+            .map(|&(id, _)| id)
+            .chain(last)
+            .map(quote)
+            .join(", ");
+        json += &format!(",\n  \"path\": [{}]", visited);
+    }
+    json + "\n}\n"
+}
+
+/// Times walking `steps` steps of a generated network through the `HashMap`
+/// it used to be parsed into against the interned network and its passes.
+fn bench(steps: usize) {
+    let rng = &mut Rng::new(0);
+    let unpack_key = |index: usize| -> String {
+        [index / 36 / 36, index / 36 % 36, index % 36]
+            .iter()
+            .map(|&digit| {
+                char::from_digit(digit as u32, 36)
+                    .unwrap()
+                    .to_ascii_uppercase()
+            })
+            .collect()
+    };
+    let mut keys = vec![START_KEY.to_string()];
+    let mut used: HashSet<String> = keys.iter().cloned().collect();
+    while keys.len() < 750 {
+        let key = unpack_key(rng.below(KEY_SPACE as u64) as usize);
+        if used.insert(key.clone()) {
+            keys.push(key);
+        }
+    }
+    let raw_instructions: String = (0..263).map(|_| *rng.pick(&['L', 'R'])).collect();
+    let entries = keys
+        .iter()
+        .map(|key| format!("{} = ({}, {})", key, rng.pick(&keys), rng.pick(&keys)))
+        .join("\n");
+    let input = format!("{}\n\n{}", raw_instructions, entries);
+    let map = parse_map(&input);
+    let start = Instant::now();
+    let mut key = START_KEY;
+    let instructions = raw_instructions.chars().map(|char| match char {
+        'L' => Instruction(0),
+        _ => Instruction(1),
+    });
+    for instruction in instructions.cycle().take(steps) {
+        let (left, right) = map[key];
+        key = match instruction {
+            Instruction(0) => left,
+            _ => right,
+        };
+        key = black_box(key);
+    }
+    println!("HashMap: {:?}", start.elapsed());
+
+    let network = Network::parse(&input).unwrap();
+    let instructions = parse_instructions(&input, &network).unwrap();
+    let start = Instant::now();
+    let passes = network.passes(&instructions, |_| false);
+    let mut id = network.id(START_KEY).unwrap();
+    for _ in 0..steps / instructions.len() {
+        id = black_box(passes[id as usize].end);
+    }
+    for &instruction in &instructions[..steps % instructions.len()] {
+        id = network.step(id, instruction).unwrap();
+    }
+    println!("Network: {:?}", start.elapsed());
+
+    assert_eq!(key, network.names[id as usize]);
+}
+
+/// Reads the instructions as single characters, or as words if they are
+/// separated by spaces or commas.
+fn parse_instructions(input: &str, network: &Network) -> Result<Vec<Instruction>, PathError> {
+    let line = input.lines().next().unwrap_or_default().trim();
+    let tokens: Vec<&str> = if line.contains([' ', ',']) {
+        line.split([' ', ','])
+            .filter(|token| !token.is_empty())
+            .collect()
+    } else {
+        line.char_indices()
+            .map(|(i, char)| &line[i..i + char.len_utf8()])
+            .collect()
+    };
+    tokens
+        .into_iter()
+        .map(|token| network.instruction(token))
         .collect()
 }
 
+/// Parses an L/R map without interning, as [`bench`]'s baseline.
 fn parse_map(input: &str) -> HashMap<&str, (&str, &str)> {
-    input.lines().skip(2).map(parse_map_entry).collect()
+    input
+        .lines()
+        .skip(2)
+        .map(|line| {
+            let (key, entry) = parse_map_entry(line).unwrap();
+            (key, (entry[0].1, entry[1].1))
+        })
+        .collect()
 }
 
-fn parse_map_entry(line: &str) -> (&str, (&str, &str)) {
-    let (raw_key, values) = line.split("=").collect_tuple().unwrap();
-    let (raw_left, raw_right) = values.split(", ").collect_tuple().unwrap();
+/// Splits `AAA = (BBB, CCC)` into its key and exits, each exit with its label
+/// if written as `left: BBB`.
+#[allow(clippy::type_complexity)]
+fn parse_map_entry(line: &str) -> Result<(&str, Vec<(Option<&str>, &str)>), PathError> {
+    let invalid_entry = || PathError::InvalidEntry(line.trim().to_string());
+    let (raw_key, values) = line.split_once('=').ok_or_else(invalid_entry)?;
+    let values = values
+        .trim()
+        .strip_prefix('(')
+        .and_then(|values| values.strip_suffix(')'))
+        .ok_or_else(invalid_entry)?;
+    let exits = values
+        .split(',')
+        .map(|exit| match exit.split_once(':') {
+            Some((label, next)) => (Some(label.trim()), next.trim()),
+            None => (None, exit.trim()),
+        })
+        .collect();
 
-    (
-        raw_key.trim(),
-        (&raw_left.trim()[1..], &raw_right[0..raw_right.len() - 1]),
-    )
+    Ok((raw_key.trim(), exits))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{part1, part2};
+    use advent_of_code_2023::{differential::compare, generate::generate, Day};
+
+    use crate::{
+        analyze, crt, first_common_hit, pack_key, parse_instructions, part1, part2,
+        render_analysis, render_dot, render_json, simulate, Analysis, Ghost, Instruction, Network,
+        PathError,
+    };
 
     #[test]
     fn test_example1() {
@@ -106,7 +1001,254 @@ mod tests {
         "#
         .trim();
 
-        assert_eq!(Some(6), part1(input));
+        assert_eq!(Ok(6), part1(input));
+    }
+
+    #[test]
+    fn test_hit_within_pass() {
+        let input = r#"
+            RL
+
+            AAA = (BBB, CCC)
+            BBB = (DDD, EEE)
+            CCC = (ZZZ, GGG)
+            DDD = (DDD, DDD)
+            EEE = (EEE, EEE)
+            GGG = (GGG, GGG)
+            ZZZ = (ZZZ, ZZZ)
+        "#
+        .trim();
+
+        assert_eq!(Ok(2), part1(input));
+    }
+
+    #[test]
+    fn test_network() {
+        let input = r#"
+            LR
+
+            AAA = (1ZB, AAA)
+            1ZB = (AAA, 1ZB)
+        "#
+        .trim();
+        let network = Network::parse(input).unwrap();
+
+        assert_eq!(Some(0), pack_key("000"));
+        assert_eq!(Some(36 * 36 * 36 - 1), pack_key("ZZZ"));
+        assert_eq!(Some(36 * 36 + 35 * 36 + 11), pack_key("1ZB"));
+        assert_eq!(None, pack_key("AAAA"));
+        assert_eq!(None, pack_key("A-A"));
+        assert_eq!(None, pack_key("aaa"));
+        assert_eq!(
+            vec![(0, "AAA"), (1, "1ZB")],
+            network.nodes().collect::<Vec<_>>()
+        );
+        assert_eq!(vec![vec![1, 0], vec![0, 1]], network.exits);
+        assert_eq!(
+            Err(PathError::InvalidKey("A-A".to_string())),
+            Network::parse("L\n\nA-A = (AAA, AAA)").map(|_| ())
+        );
+        assert_eq!(
+            Err(PathError::InvalidKey("aaa".to_string())),
+            part1("L\n\nAAA = (ZZZ, ZZZ)\naaa = (BBB, BBB)\nBBB = (BBB, BBB)\nZZZ = (ZZZ, ZZZ)")
+        );
+    }
+
+    #[test]
+    fn test_nary_networks() {
+        let positional = r#"
+            2 0 1
+
+            AAA = (AAA, BBB, CCC)
+            BBB = (BBB, ZZZ, BBB)
+            CCC = (ZZZ, CCC, CCC)
+            ZZZ = (ZZZ, ZZZ, ZZZ)
+        "#
+        .trim();
+        let labelled = r#"
+            up,down,down
+
+            AAA = (up: BBB, down: AAA)
+            BBB = (down: CCC, up: AAA)
+            CCC = (up: CCC, down: ZZZ, back: AAA)
+            ZZZ = (up: ZZZ, down: ZZZ)
+        "#
+        .trim();
+        let network = Network::parse(labelled).unwrap();
+
+        assert_eq!(Ok(2), part1(positional));
+        assert_eq!(Ok(3), part1(labelled));
+        assert_eq!(vec!["up", "down", "back"], network.labels);
+        assert_eq!(
+            Ok(vec![Instruction(0), Instruction(1), Instruction(1)]),
+            parse_instructions(labelled, &network)
+        );
+    }
+
+    #[test]
+    fn test_nary_errors() {
+        let missing_exit = r#"
+            LRL
+
+            AAA = (BBB, BBB)
+            BBB = (CCC)
+            CCC = (ZZZ, ZZZ)
+            ZZZ = (ZZZ, ZZZ)
+        "#
+        .trim();
+        let unknown_instruction = "LRX\n\nAAA = (ZZZ, ZZZ)";
+        let mixed = "L\n\nAAA = (ZZZ, right: ZZZ)";
+
+        assert_eq!(
+            Err(PathError::MissingExit {
+                key: "BBB".to_string(),
+                exit: "R".to_string()
+            }),
+            part1(missing_exit)
+        );
+        assert_eq!(
+            Err(PathError::UnknownInstruction("X".to_string())),
+            part1(unknown_instruction)
+        );
+        assert_eq!(
+            Err(PathError::InvalidEntry(
+                "AAA = (ZZZ, right: ZZZ)".to_string()
+            )),
+            part1(mixed)
+        );
+        assert_eq!(
+            Err(PathError::InvalidEntry("AAA = ZZZ".to_string())),
+            part1("L\n\nAAA = ZZZ")
+        );
+        assert_eq!(
+            Err(PathError::MissingExit {
+                key: "AAA".to_string(),
+                exit: "5".to_string()
+            }),
+            part1("5L\n\nAAA = (ZZZ, ZZZ)\nZZZ = (ZZZ, ZZZ)")
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let input = r#"
+            LRR
+
+            AAA = (BBB, ZZZ)
+            BBB = (ZZZ, AAA)
+            ZZZ = (ZZZ, CCC)
+        "#
+        .trim();
+        let network = Network::parse(input).unwrap();
+        let instructions = parse_instructions(input, &network).unwrap();
+        let path = network
+            .path("AAA", &instructions, |key| key == "ZZZ")
+            .unwrap();
+
+        assert_eq!(vec![(0, Instruction(0)), (1, Instruction(1))], path[..2]);
+        assert_eq!(
+            r#"digraph network {
+    "AAA" [style=filled, fillcolor=palegreen];
+    "BBB";
+    "ZZZ" [style=filled, fillcolor=lightcoral];
+    "CCC" [style=dashed];
+    "AAA" -> "BBB" [label="L", color=red, penwidth=2];
+    "AAA" -> "ZZZ" [label="R"];
+    "BBB" -> "ZZZ" [label="L"];
+    "BBB" -> "AAA" [label="R", color=red, penwidth=2];
+    "ZZZ" -> "ZZZ" [label="L"];
+    "ZZZ" -> "CCC" [label="R"];
+}
+"#,
+            render_dot(&network, Some(&path[..2]))
+        );
+        assert_eq!(
+            r#"{
+  "nodes": {
+    "AAA": {"L": "BBB", "R": "ZZZ"},
+    "BBB": {"L": "ZZZ", "R": "AAA"},
+    "ZZZ": {"L": "ZZZ", "R": "CCC"}
+  },
+  "starts": ["AAA"],
+  "ends": ["ZZZ"],
+  "path": ["AAA", "BBB", "AAA", "ZZZ"]
+}
+"#,
+            render_json(&network, Some(&path))
+        );
+    }
+
+    #[test]
+    fn test_analyze() {
+        let input = r#"
+            LR
+
+            11A = (11B, XXX)
+            11B = (XXX, 11Z)
+            11Z = (11B, XXX)
+            22A = (22B, XXX)
+            22B = (22C, 22C)
+            22C = (22Z, 22Z)
+            22Z = (22B, 22B)
+            XXX = (XXX, XXX)
+            QQQ = (11A, QQQ)
+        "#
+        .trim();
+        let network = Network::parse(input).unwrap();
+        let instructions = parse_instructions(input, &network).unwrap();
+        let analysis = analyze(&network, &instructions);
+
+        assert_eq!(
+            Analysis {
+                components: vec![vec![1, 3], vec![2], vec![5, 6, 7], vec![8]],
+                unreachable: vec![8],
+                dead_ends: vec![2],
+                reachable_ends: vec![(0, vec![3]), (4, vec![7])],
+            },
+            analysis
+        );
+        assert_eq!(
+            "4 cyclic components\n  2 nodes: 11B 11Z\n  1 nodes: XXX\n  3 nodes: 22B 22C 22Z\n  \
+             1 nodes: QQQ\nunreachable from any start: QQQ\ndead ends: XXX\n11A reaches: 11Z\n\
+             22A reaches: 22Z\n",
+            render_analysis(&network, &analysis)
+        );
+    }
+
+    fn simulated(input: &str, budget: u64) -> Result<Option<u64>, PathError> {
+        let network = Network::parse(input)?;
+        let instructions = parse_instructions(input, &network)?;
+        simulate(&network, &instructions, budget, budget, |_, _| {})
+    }
+
+    #[test]
+    fn test_simulate() {
+        let input = r#"
+            LR
+
+            11A = (11B, XXX)
+            11B = (XXX, 11Z)
+            11Z = (11B, XXX)
+            22A = (22B, XXX)
+            22B = (22C, 22C)
+            22C = (22Z, 22Z)
+            22Z = (22B, 22B)
+            XXX = (XXX, XXX)
+        "#
+        .trim();
+        let network = Network::parse(input).unwrap();
+        let instructions = parse_instructions(input, &network).unwrap();
+        let mut reports = Vec::new();
+        let result = simulate(&network, &instructions, 10, 2, |step, ids| {
+            reports.push((step, ids.to_vec()))
+        });
+
+        assert_eq!(Ok(Some(6)), result);
+        assert_eq!(
+            vec![(2, vec![3, 6]), (4, vec![3, 5]), (6, vec![3, 7])],
+            reports
+        );
+        assert_eq!(Ok(None), simulated(input, 5));
     }
 
     #[test]
@@ -125,6 +1267,218 @@ mod tests {
         "#
         .trim();
 
-        assert_eq!(6, part2(input));
+        assert_eq!(Ok(6), part2(input));
+        assert_eq!(Ok(Some(6)), simulated(input, 100));
+    }
+
+    #[test]
+    fn test_tail_differs_from_cycle() {
+        // 11A arrives on every even step, 22A on steps 1, 4, 7, ...
+        let input = r#"
+            L
+
+            11A = (11B, XXX)
+            11B = (11Z, XXX)
+            11Z = (11B, XXX)
+            22A = (22Z, XXX)
+            22B = (22C, XXX)
+            22C = (22Z, XXX)
+            22Z = (22B, XXX)
+            XXX = (XXX, XXX)
+        "#
+        .trim();
+
+        assert_eq!(Ok(4), part2(input));
+        assert_eq!(Ok(Some(4)), simulated(input, 100));
+    }
+
+    #[test]
+    fn test_several_hits_per_cycle() {
+        // 11A arrives on steps 2, 3, 5, 6, 8, ... and 33A on every 4th.
+        let input = r#"
+            L
+
+            11A = (11Y, XXX)
+            11Y = (11Z, XXX)
+            11Z = (22Z, XXX)
+            22Z = (11Y, XXX)
+            33A = (33B, XXX)
+            33B = (33C, XXX)
+            33C = (33D, XXX)
+            33D = (33Z, XXX)
+            33Z = (33B, XXX)
+            XXX = (XXX, XXX)
+        "#
+        .trim();
+
+        let network = Network::parse(input).unwrap();
+        let instructions = parse_instructions(input, &network).unwrap();
+        let start = network.id("11A").unwrap();
+        let ghost = Ghost::walk(&network, start, &instructions, |key| key.ends_with("Z"));
+        assert_eq!(
+            Ok(Ghost {
+                tail: 1,
+                cycle: 3,
+                tail_hits: vec![],
+                cycle_hits: vec![2, 3],
+                stuck: None,
+            }),
+            ghost
+        );
+        let ghost = ghost.unwrap();
+        assert_eq!(
+            vec![2, 3, 5, 6, 8, 9],
+            (1..10).filter(|&step| ghost.hits(step)).collect::<Vec<_>>()
+        );
+        assert_eq!(Ok(8), part2(input));
+        assert_eq!(Ok(Some(8)), simulated(input, 100));
+    }
+
+    #[test]
+    fn test_never_together() {
+        let ghosts = [
+            Ghost {
+                tail: 1,
+                cycle: 2,
+                tail_hits: vec![],
+                cycle_hits: vec![2],
+                stuck: None,
+            },
+            Ghost {
+                tail: 1,
+                cycle: 4,
+                tail_hits: vec![],
+                cycle_hits: vec![3],
+                stuck: None,
+            },
+        ];
+
+        assert_eq!(None, first_common_hit(&ghosts));
+    }
+
+    #[test]
+    fn test_path_errors() {
+        let unreachable = r#"
+            LR
+
+            AAA = (BBB, BBB)
+            BBB = (AAA, CCC)
+            CCC = (CCC, AAA)
+            ZZZ = (ZZZ, ZZZ)
+        "#
+        .trim();
+        let unknown = r#"
+            L
+
+            AAA = (BBB, BBB)
+            ZZZ = (ZZZ, ZZZ)
+        "#
+        .trim();
+
+        assert_eq!(
+            Err(PathError::Unreachable("AAA".to_string())),
+            part1(unreachable)
+        );
+        assert_eq!(
+            Err(PathError::UnknownNode("BBB".to_string())),
+            part1(unknown)
+        );
+        assert_eq!(
+            Err(PathError::Unreachable("AAA".to_string())),
+            part2(unreachable)
+        );
+        assert_eq!(
+            Err(PathError::UnknownNode("BBB".to_string())),
+            part2(unknown)
+        );
+
+        let no_instructions = "\n\nAAA = (ZZZ, ZZZ)\nZZZ = (ZZZ, ZZZ)";
+        assert_eq!(
+            Err(PathError::Unreachable("AAA".to_string())),
+            part1(no_instructions)
+        );
+        assert_eq!(
+            Err(PathError::Unreachable("AAA".to_string())),
+            part2(no_instructions)
+        );
+        assert_eq!(Ok(None), simulated(no_instructions, 100));
+    }
+
+    #[test]
+    fn test_stuck_ghosts() {
+        let meet_first = r#"
+            L
+
+            11A = (11Z, 11Z)
+            11Z = (QQQ, QQQ)
+            22A = (22Z, 22Z)
+            22Z = (22Z, 22Z)
+        "#
+        .trim();
+        let stuck_first = r#"
+            L
+
+            11A = (11Z, 11Z)
+            11Z = (QQQ, QQQ)
+            22A = (22B, 22B)
+            22B = (22Z, 22Z)
+            22Z = (22Z, 22Z)
+        "#
+        .trim();
+
+        let network = Network::parse(meet_first).unwrap();
+        let instructions = parse_instructions(meet_first, &network).unwrap();
+        let start = network.id("11A").unwrap();
+        assert_eq!(
+            Ok(Ghost {
+                tail: 3,
+                cycle: 0,
+                tail_hits: vec![1],
+                cycle_hits: vec![],
+                stuck: Some(PathError::UnknownNode("QQQ".to_string())),
+            }),
+            Ghost::walk(&network, start, &instructions, |key| key.ends_with("Z"))
+        );
+        assert_eq!(Ok(1), part2(meet_first));
+        assert_eq!(Ok(Some(1)), simulated(meet_first, 100));
+        assert_eq!(
+            Err(PathError::UnknownNode("QQQ".to_string())),
+            part2(stuck_first)
+        );
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(Some((4, 6)), crt((0, 2), (1, 3)));
+        assert_eq!(Some((10, 12)), crt((4, 6), (2, 4)));
+        assert_eq!(None, crt((0, 2), (1, 4)));
+    }
+
+    #[test]
+    fn test_generated() {
+        for seed in 0..20 {
+            let input = generate(Day::DAY8, seed, 50);
+            let network = Network::parse(&input).unwrap();
+
+            assert_eq!(50, network.nodes().count());
+            assert!(parse_instructions(&input, &network).is_ok());
+            assert!(matches!(
+                part1(&input),
+                Ok(_) | Err(PathError::Unreachable(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_differential() {
+        const BUDGET: u64 = 10_000;
+
+        compare(
+            0..200,
+            |seed| generate(Day::DAY8, seed, 8),
+            |input| simulated(input, BUDGET).ok().flatten(),
+            |input| part2(input).ok().filter(|&steps| steps <= BUDGET),
+        )
+        .unwrap_or_else(|disagreement| panic!("{}", disagreement));
     }
 }
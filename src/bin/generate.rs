@@ -0,0 +1,37 @@
+use std::str::FromStr;
+
+use advent_of_code_2023::{
+    generate::{default_size, generate},
+    Day,
+};
+
+/// Prints a generated input, as in `generate day5 --seed=1 --size=10`.
+fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let day = args
+        .first()
+        .and_then(|day| Day::from_str(day).ok())
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "expected a day, like day5",
+            )
+        })?;
+    let invalid_input = |error| std::io::Error::new(std::io::ErrorKind::InvalidInput, error);
+    let seed = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--seed="))
+        .map(|seed| seed.parse().map_err(invalid_input))
+        .transpose()?
+        .unwrap_or(0);
+    let size = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--size="))
+        .map(|size| size.parse().map_err(invalid_input))
+        .transpose()?
+        .unwrap_or(default_size(day));
+
+    print!("{}", generate(day, seed, size));
+
+    Ok(())
+}
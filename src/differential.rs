@@ -0,0 +1,121 @@
+//! Differential testing: run a reference solver and an optimized one on the
+//! same generated inputs, and shrink the first input they disagree on.
+
+use std::{fmt, ops::Range};
+
+/// An input, as small as [`minimize`] could make it, on which two solvers give
+/// different answers.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Disagreement<T> {
+    pub seed: u64,
+    pub input: String,
+    pub reference: T,
+    pub optimized: T,
+}
+
+impl<T: fmt::Debug> fmt::Display for Disagreement<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "seed {}: the reference gives {:?} but the optimized solver {:?} for",
+            self.seed, self.reference, self.optimized
+        )?;
+        write!(f, "{}", self.input)
+    }
+}
+
+/// Compares `reference` and `optimized` on the input `generate` makes for each
+/// of `seeds`. The solvers should give some answer, like `None`, for any subset
+/// of an input's lines, since that is how a disagreement is shrunk.
+pub fn compare<T: PartialEq>(
+    seeds: Range<u64>,
+    generate: impl Fn(u64) -> String,
+    reference: impl Fn(&str) -> T,
+    optimized: impl Fn(&str) -> T,
+) -> Result<(), Disagreement<T>> {
+    let disagrees = |input: &str| reference(input) != optimized(input);
+    match seeds
+        .map(|seed| (seed, generate(seed)))
+        .find(|(_, input)| disagrees(input))
+    {
+        Some((seed, input)) => {
+            let input = minimize(&input, disagrees);
+            Err(Disagreement {
+                seed,
+                reference: reference(&input),
+                optimized: optimized(&input),
+                input,
+            })
+        }
+        None => Ok(()),
+    }
+}
+
+/// Drops lines from `input`, in ever smaller runs, for as long as `fails` still
+/// holds for what is left.
+pub fn minimize(input: &str, fails: impl Fn(&str) -> bool) -> String {
+    let render =
+        |lines: &[&str]| -> String { lines.iter().map(|line| format!("{}\n", line)).collect() };
+    let mut lines: Vec<&str> = input.lines().collect();
+    let mut run = lines.len().div_ceil(2);
+    while run > 0 {
+        let mut start = 0;
+        while start < lines.len() {
+            let end = (start + run).min(lines.len());
+            let candidate = [&lines[..start], &lines[end..]].concat();
+            if fails(&render(&candidate)) {
+                lines = candidate;
+            } else {
+                start = end;
+            }
+        }
+        run /= 2;
+    }
+    render(&lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::differential::{compare, minimize, Disagreement};
+
+    #[test]
+    fn test_minimize() {
+        let input = "1\n2\n3\n4\n5\n6\n7\n";
+        let has = |line: &'static str| move |input: &str| input.lines().any(|l| l == line);
+
+        assert_eq!("5\n", minimize(input, has("5")));
+        assert_eq!(
+            "2\n6\n",
+            minimize(input, |input| has("2")(input) && has("6")(input))
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let generate = |seed: u64| (0..10).map(|n| format!("{}\n", n * seed)).collect();
+        let sum = |input: &str| {
+            input
+                .lines()
+                .map(|n| n.parse::<u64>().unwrap())
+                .sum::<u64>()
+        };
+        let off_past_50 = |input: &str| {
+            input
+                .lines()
+                .map(|n| n.parse::<u64>().unwrap())
+                .map(|n| if n > 50 { n + 1 } else { n })
+                .sum::<u64>()
+        };
+
+        assert_eq!(Ok(()), compare(0..5, generate, sum, sum));
+        assert_eq!(
+            Err(Disagreement {
+                seed: 6,
+                input: "54\n".to_string(),
+                reference: 54,
+                optimized: 55
+            }),
+            compare(0..10, generate, sum, off_past_50)
+        );
+    }
+}
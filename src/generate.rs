@@ -0,0 +1,311 @@
+//! Random puzzle inputs, reproducible from a seed, for stressing the parsers
+//! and solvers beyond the one real input per day.
+
+use std::collections::HashSet;
+
+use itertools::Itertools;
+
+use crate::Day;
+
+/// A xorshift generator. Not for anything but test data.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // Spread the seed out so that small seeds don't start in a run of zeros,
+        // and never land on the all-zero state xorshift can't leave.
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Rng((state ^ (state >> 31)).max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `0..bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// A number in `low..=high`.
+    pub fn between(&mut self, low: u64, high: u64) -> u64 {
+        low + self.below(high - low + 1)
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i as u64 + 1) as usize);
+        }
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+
+    /// `count` distinct numbers in `low..=high`, in the order drawn.
+    pub fn distinct(&mut self, count: usize, low: u64, high: u64) -> Vec<u64> {
+        let mut drawn = Vec::new();
+        while drawn.len() < count {
+            let n = self.between(low, high);
+            if !drawn.contains(&n) {
+                drawn.push(n);
+            }
+        }
+        drawn
+    }
+}
+
+/// A size close to the real inputs', or as large as the day allows.
+pub fn default_size(day: Day) -> usize {
+    match day {
+        Day::DAY2 => 100,
+        Day::DAY3 => 140,
+        Day::DAY6 => 4,
+        _ => 200,
+    }
+}
+
+/// An input for `day` with roughly `size` lines, cards, games or nodes.
+pub fn generate(day: Day, seed: u64, size: usize) -> String {
+    let rng = &mut Rng::new(seed);
+    match day {
+        Day::DAY1 => day1(rng, size),
+        Day::DAY2 => day2(rng, size),
+        Day::DAY3 => day3(rng, size),
+        Day::DAY4 => day4(rng, size),
+        Day::DAY5 => day5(rng, size),
+        Day::DAY6 => day6(rng, size),
+        Day::DAY7 => day7(rng, size),
+        Day::DAY8 => day8(rng, size),
+    }
+}
+
+const DIGIT_NAMES: [&str; 9] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+
+/// Calibration lines of letters, digits and spelled-out digits, each with at
+/// least one digit.
+pub fn day1(rng: &mut Rng, lines: usize) -> String {
+    (0..lines)
+        .map(|_| {
+            let mut line: Vec<String> = (0..rng.between(1, 8))
+                .map(|_| match rng.below(3) {
+                    0 => rng.between(1, 9).to_string(),
+                    1 => rng.pick(&DIGIT_NAMES).to_string(),
+                    _ => char::from(b'a' + rng.below(26) as u8).to_string(),
+                })
+                .collect();
+            let at = rng.below(line.len() as u64 + 1) as usize;
+            line.insert(at, rng.between(1, 9).to_string());
+            line.concat() + "\n"
+        })
+        .collect()
+}
+
+/// Games of up to six draws. Game ids are a `u8`, so there are at most 255.
+pub fn day2(rng: &mut Rng, games: usize) -> String {
+    (1..=games.min(u8::MAX as usize))
+        .map(|id| {
+            let draws = (0..rng.between(1, 6))
+                .map(|_| {
+                    let colors = ["red", "green", "blue"];
+                    let count = rng.between(1, 3) as usize;
+                    rng.distinct(count, 0, 2)
+                        .iter()
+                        .map(|&color| format!("{} {}", rng.between(1, 20), colors[color as usize]))
+                        .join(", ")
+                })
+                .join("; ");
+            format!("Game {}: {}\n", id, draws)
+        })
+        .collect()
+}
+
+/// A `side` by `side` schematic of numbers below 1000 and symbols.
+pub fn day3(rng: &mut Rng, side: usize) -> String {
+    (0..side)
+        .map(|_| {
+            let mut row = String::new();
+            while row.len() < side {
+                let after_number = row.ends_with(|char: char| char.is_ascii_digit());
+                match rng.below(10) {
+                    0..=1 if !after_number => {
+                        let number = rng.between(1, 999).to_string();
+                        row += &number[..number.len().min(side - row.len())];
+                    }
+                    2 => row.push(*rng.pick(&['*', '#', '+', '$', '/', '@', '=', '%', '&', '-'])),
+                    _ => row.push('.'),
+                }
+            }
+            row + "\n"
+        })
+        .collect()
+}
+
+/// Scratchcards with 10 winning numbers and 25 numbers each. No card wins
+/// copies of cards past the end of the table.
+pub fn day4(rng: &mut Rng, cards: usize) -> String {
+    let padded = |nums: &[u64]| nums.iter().map(|n| format!("{:>2}", n)).join(" ");
+    (1..=cards)
+        .map(|id| {
+            let winning_nums = rng.distinct(10, 1, 99);
+            // Mostly few matches, as copies multiply quickly.
+            let matches = match rng.below(10) {
+                0 => rng.between(0, 10),
+                _ => rng.below(3),
+            };
+            let matches = matches.min((cards - id) as u64) as usize;
+            let mut nums = winning_nums[..matches].to_vec();
+            while nums.len() < 25 {
+                let n = rng.between(1, 99);
+                if !winning_nums.contains(&n) && !nums.contains(&n) {
+                    nums.push(n);
+                }
+            }
+            rng.shuffle(&mut nums);
+            format!(
+                "Card {:>3}: {} | {}\n",
+                id,
+                padded(&winning_nums),
+                padded(&nums)
+            )
+        })
+        .collect()
+}
+
+/// An almanac with `size` seed ranges and up to `size` ranges per map, over
+/// numbers below 2^32. Ranges within a map never overlap.
+pub fn day5(rng: &mut Rng, size: usize) -> String {
+    almanac(rng, size, 1 << 32)
+}
+
+/// Like [`day5`], over numbers below `limit`, which must be at least twice
+/// `size`. Small limits make inputs that can be checked seed by seed.
+pub fn almanac(rng: &mut Rng, size: usize, limit: u64) -> String {
+    let categories = [
+        "seed",
+        "soil",
+        "fertilizer",
+        "water",
+        "light",
+        "temperature",
+        "humidity",
+        "location",
+    ];
+    let seeds = (0..size)
+        .map(|_| {
+            let start = rng.below(limit);
+            let len = rng.between(1, (limit - start).min((limit / 16).max(1)));
+            format!("{} {}", start, len)
+        })
+        .join(" ");
+    let maps = categories
+        .iter()
+        .tuple_windows()
+        .map(|(source, destination)| {
+            let count = rng.between(1, size.max(1) as u64) as usize;
+            let mut bounds = rng.distinct(2 * count, 0, limit - 1);
+            bounds.sort();
+            let mut ranges: Vec<String> = bounds
+                .chunks(2)
+                .map(|bound| {
+                    let len = bound[1] - bound[0];
+                    format!("{} {} {}", rng.below(limit - len), bound[0], len)
+                })
+                .collect();
+            rng.shuffle(&mut ranges);
+            let ranges = ranges.join("\n");
+            format!("{}-to-{} map:\n{}\n", source, destination, ranges)
+        })
+        .join("\n");
+    format!("seeds: {}\n\n{}", seeds, maps)
+}
+
+/// Races of under 100ms, each winnable. Part 2 joins the numbers, so more than
+/// about four races overflow it.
+pub fn day6(rng: &mut Rng, races: usize) -> String {
+    let times: Vec<u64> = (0..races).map(|_| rng.between(2, 99)).collect();
+    let distances: Vec<u64> = times
+        .iter()
+        .map(|&time| rng.below(time / 2 * (time - time / 2)))
+        .collect();
+    let row = |nums: &[u64]| nums.iter().map(|n| format!("{:>6}", n)).collect::<String>();
+    format!("Time:    {}\nDistance:{}\n", row(&times), row(&distances))
+}
+
+/// Five card hands with bids up to 1000.
+pub fn day7(rng: &mut Rng, hands: usize) -> String {
+    let cards = [
+        '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K', 'A',
+    ];
+    (0..hands)
+        .map(|_| {
+            let hand: String = (0..5).map(|_| *rng.pick(&cards)).collect();
+            format!("{} {}\n", hand, rng.between(1, 1000))
+        })
+        .collect()
+}
+
+/// A network of `nodes` nodes including `AAA` and `ZZZ`, with about one in ten
+/// of the others a `..A` start or `..Z` end. Ends aren't always reachable.
+/// There are only 36^3 keys, so there are never more nodes than that.
+pub fn day8(rng: &mut Rng, nodes: usize) -> String {
+    let digits: Vec<char> = ('0'..='9').chain('A'..='Z').collect();
+    let mut keys = vec!["AAA".to_string(), "ZZZ".to_string()];
+    let mut used: HashSet<String> = keys.iter().cloned().collect();
+    while keys.len() < nodes.clamp(2, digits.len().pow(3)) {
+        let last = match rng.below(10) {
+            0 => 'A',
+            1 => 'Z',
+            _ => loop {
+                let last = *rng.pick(&digits);
+                if last != 'A' && last != 'Z' {
+                    break last;
+                }
+            },
+        };
+        let key = format!("{}{}{}", rng.pick(&digits), rng.pick(&digits), last);
+        if used.insert(key.clone()) {
+            keys.push(key);
+        }
+    }
+    let instructions: String = (0..rng.between(1, 30))
+        .map(|_| *rng.pick(&['L', 'R']))
+        .collect();
+    let entries: String = keys
+        .iter()
+        .map(|key| format!("{} = ({}, {})\n", key, rng.pick(&keys), rng.pick(&keys)))
+        .collect();
+    format!("{}\n\n{}", instructions, entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use crate::{generate::generate, Day};
+
+    #[test]
+    fn test_seeded() {
+        for day in Day::iter() {
+            let input = generate(day, 7, 20);
+            assert!(!input.is_empty());
+            assert!(input.ends_with('\n'));
+        }
+        assert_eq!(generate(Day::DAY5, 7, 20), generate(Day::DAY5, 7, 20));
+        assert_ne!(generate(Day::DAY5, 7, 20), generate(Day::DAY5, 8, 20));
+    }
+
+    #[test]
+    fn test_day8_key_space() {
+        let input = generate(Day::DAY8, 7, 50_000);
+
+        assert_eq!(36 * 36 * 36 + 2, input.lines().count());
+    }
+}
@@ -1,8 +1,11 @@
-use std::{fs, path::PathBuf};
+use std::{fs, io, path::PathBuf};
 
-use strum::{Display, EnumString};
+use strum::{Display, EnumIter, EnumString};
 
-#[derive(EnumString, Display)]
+pub mod differential;
+pub mod generate;
+
+#[derive(EnumString, Display, EnumIter, Clone, Copy, Debug, PartialEq, Eq)]
 #[strum(serialize_all = "snake_case")]
 pub enum Day {
     DAY1,
@@ -15,7 +18,20 @@ pub enum Day {
     DAY8,
 }
 
+/// Reads the day's puzzle input, or generates one when run with `--seed=N`
+/// and optionally `--size=N`.
 pub fn read_input(day: Day) -> std::io::Result<String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(seed) = args.iter().find_map(|arg| arg.strip_prefix("--seed=")) {
+        let invalid_input = |e| io::Error::new(io::ErrorKind::InvalidInput, e);
+        let seed = seed.parse().map_err(invalid_input)?;
+        let size = match args.iter().find_map(|arg| arg.strip_prefix("--size=")) {
+            Some(size) => size.parse().map_err(invalid_input)?,
+            None => generate::default_size(day),
+        };
+        return Ok(generate::generate(day, seed, size));
+    }
+
     let mut base = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     let path = format!("data/{}/input.txt", day);
     base.push("data/");